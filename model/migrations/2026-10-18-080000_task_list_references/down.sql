@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE task DROP CONSTRAINT task_list_c;
+ALTER TABLE task_list DROP CONSTRAINT task_list_project_c;
+ALTER TABLE task_list DROP COLUMN position;
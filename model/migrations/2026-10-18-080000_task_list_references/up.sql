@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE task_list ADD COLUMN position INT NOT NULL DEFAULT 0;
+DELETE FROM task_list WHERE project_id NOT IN (SELECT id FROM project);
+ALTER TABLE task_list ADD CONSTRAINT task_list_project_c FOREIGN KEY (project_id) REFERENCES project(id) ON DELETE CASCADE;
+UPDATE task SET task_list_id = NULL WHERE task_list_id NOT IN (SELECT id FROM task_list);
+ALTER TABLE task ADD CONSTRAINT task_list_c FOREIGN KEY (task_list_id) REFERENCES task_list(id) ON DELETE CASCADE;
@@ -0,0 +1,2 @@
+DROP TABLE password_credential;
+DROP INDEX app_user_email_idx;
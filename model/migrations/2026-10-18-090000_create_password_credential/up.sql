@@ -0,0 +1,6 @@
+CREATE UNIQUE INDEX app_user_email_idx ON app_user (email);
+
+CREATE TABLE password_credential (
+	user_id INT PRIMARY KEY REFERENCES app_user(id) ON DELETE CASCADE,
+	password_hash VARCHAR NOT NULL
+);
@@ -0,0 +1,12 @@
+CREATE TABLE api_token (
+	id SERIAL PRIMARY KEY,
+	user_id INT NOT NULL REFERENCES app_user(id) ON DELETE CASCADE,
+	name VARCHAR NOT NULL,
+	token_hash VARCHAR NOT NULL UNIQUE,
+	scope VARCHAR NOT NULL DEFAULT 'read_write' CHECK (scope IN ('read_only', 'read_write')),
+	expires_at TIMESTAMPTZ,
+	last_used_at TIMESTAMPTZ,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX api_token_user_idx ON api_token (user_id);
@@ -0,0 +1,11 @@
+CREATE TABLE project_member (
+	project_id INT NOT NULL REFERENCES project(id) ON DELETE CASCADE,
+	user_id INT NOT NULL REFERENCES app_user(id) ON DELETE CASCADE,
+	role VARCHAR NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+	PRIMARY KEY (project_id, user_id)
+);
+
+CREATE INDEX project_member_user_idx ON project_member (user_id);
+
+INSERT INTO project_member (project_id, user_id, role)
+SELECT id, owner_id, 'owner' FROM project;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX task_position_idx;
+ALTER TABLE task DROP COLUMN position;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE task ADD COLUMN position INT NOT NULL DEFAULT 0;
+UPDATE task SET position = numbered.position
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY project_id, task_list_id ORDER BY id) - 1 AS position
+    FROM task
+) AS numbered
+WHERE task.id = numbered.id;
+CREATE INDEX task_position_idx ON task (project_id, task_list_id, position);
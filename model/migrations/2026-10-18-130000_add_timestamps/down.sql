@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER set_updated_at ON app_user;
+ALTER TABLE app_user DROP COLUMN created_at, DROP COLUMN updated_at;
+DROP TRIGGER set_updated_at ON project;
+ALTER TABLE project DROP COLUMN created_at, DROP COLUMN updated_at;
+DROP TRIGGER set_updated_at ON task;
+ALTER TABLE task DROP COLUMN created_at, DROP COLUMN updated_at;
+DROP TRIGGER set_updated_at ON task_list;
+ALTER TABLE task_list DROP COLUMN created_at, DROP COLUMN updated_at;
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+ALTER TABLE app_user
+	ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+	ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+SELECT diesel_manage_updated_at('app_user');
+
+ALTER TABLE project
+	ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+	ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+SELECT diesel_manage_updated_at('project');
+
+ALTER TABLE task
+	ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+	ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+SELECT diesel_manage_updated_at('task');
+
+ALTER TABLE task_list
+	ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+	ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+SELECT diesel_manage_updated_at('task_list');
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX task_due_at_idx;
+ALTER TABLE task DROP COLUMN due_at, DROP COLUMN remind_at;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE task
+	ADD COLUMN due_at TIMESTAMPTZ,
+	ADD COLUMN remind_at TIMESTAMPTZ;
+CREATE INDEX task_due_at_idx ON task (due_at) WHERE due_at IS NOT NULL;
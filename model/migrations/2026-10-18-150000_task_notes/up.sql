@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE task ADD COLUMN notes TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE checklist_item;
+DROP FUNCTION checklist_rollup();
+ALTER TABLE task DROP COLUMN checklist_total, DROP COLUMN checklist_done;
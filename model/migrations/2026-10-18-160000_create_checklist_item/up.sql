@@ -0,0 +1,36 @@
+CREATE TABLE checklist_item (
+	id SERIAL PRIMARY KEY,
+	task_id INT NOT NULL REFERENCES task(id) ON DELETE CASCADE,
+	title VARCHAR NOT NULL,
+	done BOOLEAN NOT NULL DEFAULT false,
+	position INT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX checklist_item_task_idx ON checklist_item (task_id, position);
+
+-- rollup of the checklist, kept up to date by the trigger below
+ALTER TABLE task
+	ADD COLUMN checklist_total INT NOT NULL DEFAULT 0,
+	ADD COLUMN checklist_done INT NOT NULL DEFAULT 0;
+
+CREATE OR REPLACE FUNCTION checklist_rollup() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        UPDATE task SET
+            checklist_total = (SELECT count(*) FROM checklist_item WHERE task_id = OLD.task_id),
+            checklist_done = (SELECT count(*) FROM checklist_item WHERE task_id = OLD.task_id AND done)
+        WHERE id = OLD.task_id;
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        UPDATE task SET
+            checklist_total = (SELECT count(*) FROM checklist_item WHERE task_id = NEW.task_id),
+            checklist_done = (SELECT count(*) FROM checklist_item WHERE task_id = NEW.task_id AND done)
+        WHERE id = NEW.task_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER checklist_item_rollup
+    AFTER INSERT OR DELETE OR UPDATE OF task_id, done ON checklist_item
+    FOR EACH ROW EXECUTE PROCEDURE checklist_rollup();
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE task_label;
+DROP TABLE label;
@@ -0,0 +1,15 @@
+CREATE TABLE label (
+	id SERIAL PRIMARY KEY,
+	project_id INT NOT NULL REFERENCES project(id) ON DELETE CASCADE,
+	name VARCHAR NOT NULL,
+	color VARCHAR NOT NULL DEFAULT '#808080' CHECK (color ~ '^#[0-9a-fA-F]{6}$'),
+	UNIQUE (project_id, name)
+);
+
+CREATE TABLE task_label (
+	task_id INT NOT NULL REFERENCES task(id) ON DELETE CASCADE,
+	label_id INT NOT NULL REFERENCES label(id) ON DELETE CASCADE,
+	PRIMARY KEY (task_id, label_id)
+);
+
+CREATE INDEX task_label_label_idx ON task_label (label_id);
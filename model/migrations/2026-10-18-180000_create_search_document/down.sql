@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER task_search ON task;
+DROP TRIGGER task_list_search ON task_list;
+DROP TRIGGER project_search ON project;
+DROP FUNCTION task_search_sync();
+DROP FUNCTION task_list_search_sync();
+DROP FUNCTION project_search_sync();
+DROP FUNCTION search_document_upsert(VARCHAR, INT, INT, VARCHAR);
+DROP TABLE search_document;
@@ -0,0 +1,76 @@
+-- one row per searchable project, task list and task, kept up to date by the
+-- triggers below and queried through `/api/search`
+CREATE TABLE search_document (
+	kind VARCHAR NOT NULL CHECK (kind IN ('project', 'task_list', 'task')),
+	entity_id INT NOT NULL,
+	project_id INT NOT NULL REFERENCES project(id) ON DELETE CASCADE,
+	title VARCHAR NOT NULL,
+	document TSVECTOR NOT NULL,
+	PRIMARY KEY (kind, entity_id)
+);
+
+CREATE INDEX search_document_document_idx ON search_document USING GIN (document);
+CREATE INDEX search_document_project_idx ON search_document (project_id);
+
+CREATE OR REPLACE FUNCTION search_document_upsert(_kind VARCHAR, _id INT, _project_id INT, _title VARCHAR) RETURNS void AS $$
+BEGIN
+    INSERT INTO search_document (kind, entity_id, project_id, title, document)
+    VALUES (_kind, _id, _project_id, _title, to_tsvector('english', _title))
+    ON CONFLICT (kind, entity_id) DO UPDATE SET
+        project_id = EXCLUDED.project_id,
+        title = EXCLUDED.title,
+        document = EXCLUDED.document;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION project_search_sync() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        DELETE FROM search_document WHERE kind = 'project' AND entity_id = OLD.id;
+    ELSE
+        PERFORM search_document_upsert('project', NEW.id, NEW.id, NEW.title);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION task_list_search_sync() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        DELETE FROM search_document WHERE kind = 'task_list' AND entity_id = OLD.id;
+    ELSE
+        PERFORM search_document_upsert('task_list', NEW.id, NEW.project_id, NEW.title);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION task_search_sync() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        DELETE FROM search_document WHERE kind = 'task' AND entity_id = OLD.id;
+    ELSE
+        PERFORM search_document_upsert('task', NEW.id, NEW.project_id, NEW.title);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER project_search
+    AFTER INSERT OR DELETE OR UPDATE OF title ON project
+    FOR EACH ROW EXECUTE PROCEDURE project_search_sync();
+
+CREATE TRIGGER task_list_search
+    AFTER INSERT OR DELETE OR UPDATE OF title, project_id ON task_list
+    FOR EACH ROW EXECUTE PROCEDURE task_list_search_sync();
+
+CREATE TRIGGER task_search
+    AFTER INSERT OR DELETE OR UPDATE OF title, project_id ON task
+    FOR EACH ROW EXECUTE PROCEDURE task_search_sync();
+
+INSERT INTO search_document (kind, entity_id, project_id, title, document)
+SELECT 'project', id, id, title, to_tsvector('english', title) FROM project
+UNION ALL
+SELECT 'task_list', id, project_id, title, to_tsvector('english', title) FROM task_list
+UNION ALL
+SELECT 'task', id, project_id, title, to_tsvector('english', title) FROM task;
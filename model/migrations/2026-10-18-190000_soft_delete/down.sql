@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE task DROP COLUMN deleted_at;
+ALTER TABLE project DROP COLUMN deleted_at;
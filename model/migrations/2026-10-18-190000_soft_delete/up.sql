@@ -0,0 +1,6 @@
+-- trashed rows keep their data until the purge job deletes them for good
+ALTER TABLE project ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE task ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX project_deleted_at_idx ON project (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX task_deleted_at_idx ON task (deleted_at) WHERE deleted_at IS NOT NULL;
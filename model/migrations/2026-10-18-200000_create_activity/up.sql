@@ -0,0 +1,13 @@
+-- who changed what in a project, written in the transaction of the change
+CREATE TABLE activity (
+	id SERIAL PRIMARY KEY,
+	project_id INT NOT NULL REFERENCES project(id) ON DELETE CASCADE,
+	actor_id INT NOT NULL REFERENCES app_user(id),
+	entity_type VARCHAR NOT NULL,
+	entity_id INT NOT NULL,
+	action VARCHAR NOT NULL,
+	diff JSONB NOT NULL DEFAULT '{}',
+	created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX activity_project_idx ON activity (project_id, created_at DESC, id);
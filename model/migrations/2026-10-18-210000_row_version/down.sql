@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE task DROP COLUMN version;
+ALTER TABLE project DROP COLUMN version;
@@ -0,0 +1,3 @@
+-- bumped by every edit through PATCH, clients send it back to detect lost updates
+ALTER TABLE project ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE task ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+DROP INDEX app_user_email_idx;
+CREATE UNIQUE INDEX app_user_email_idx ON app_user (email);
@@ -0,0 +1,4 @@
+-- emails are compared case-insensitively, see AppUser::normalize_email
+UPDATE app_user SET email = lower(trim(email)) WHERE email <> lower(trim(email));
+DROP INDEX app_user_email_idx;
+CREATE UNIQUE INDEX app_user_email_idx ON app_user (lower(email));
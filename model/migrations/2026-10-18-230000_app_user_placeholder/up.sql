@@ -0,0 +1,2 @@
+-- users created by an invite, register and sign-in claim them
+ALTER TABLE app_user ADD COLUMN placeholder BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+ALTER TABLE task DROP CONSTRAINT task_list_c;
+ALTER TABLE task ADD CONSTRAINT task_list_c FOREIGN KEY (task_list_id) REFERENCES task_list(id) ON DELETE CASCADE;
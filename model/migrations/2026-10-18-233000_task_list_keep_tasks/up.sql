@@ -0,0 +1,3 @@
+-- deleting a task list trashes its tasks first, the list is then only taken off them
+ALTER TABLE task DROP CONSTRAINT task_list_c;
+ALTER TABLE task ADD CONSTRAINT task_list_c FOREIGN KEY (task_list_id) REFERENCES task_list(id) ON DELETE SET NULL;
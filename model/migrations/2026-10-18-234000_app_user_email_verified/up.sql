@@ -0,0 +1,6 @@
+-- set once an identity provider vouched for the email, password accounts stay
+-- unverified until then
+ALTER TABLE app_user ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE app_user SET email_verified = TRUE
+WHERE NOT placeholder
+  AND NOT EXISTS (SELECT 1 FROM password_credential c WHERE c.user_id = app_user.id);
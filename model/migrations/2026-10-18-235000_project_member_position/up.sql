@@ -0,0 +1,5 @@
+-- every member orders their own project list, seeded from the shared priority
+ALTER TABLE project_member ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+UPDATE project_member SET position = p.priority
+FROM project p
+WHERE p.id = project_member.project_id;
@@ -1,4 +1,5 @@
 pub mod models;
+pub mod query;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod schema;
\ No newline at end of file
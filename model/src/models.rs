@@ -4,6 +4,20 @@ use super::schema::*;
 use diesel::prelude::*;
 #[cfg(not(target_arch = "wasm32"))]
 use diesel::PgConnection;
+#[cfg(not(target_arch = "wasm32"))]
+use diesel::deserialize::{self, FromSql};
+#[cfg(not(target_arch = "wasm32"))]
+use diesel::pg::{Pg, PgValue};
+#[cfg(not(target_arch = "wasm32"))]
+use diesel::serialize::{self, IsNull, Output, ToSql};
+#[cfg(not(target_arch = "wasm32"))]
+use diesel::sql_types::Text;
+#[cfg(not(target_arch = "wasm32"))]
+use diesel::{AsExpression, FromSqlRow};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
+use crate::query::Cursor;
+use chrono::{DateTime, Utc};
 use serde_derive::{Deserialize, Serialize};
 
 #[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=project))]
@@ -13,7 +27,14 @@ pub struct Project {
     pub id: i32,
     pub owner_id: i32,
     pub title: String,
+    /// Where the project sits in the caller's own project list.
     pub priority: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// When the project was moved to the trash.
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Counts the edits, served as the `ETag` of the project.
+    pub version: i32,
 }
 
 
@@ -35,14 +56,87 @@ impl Project {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Identifiable, Associations, Queryable))]
 #[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(Project)))]
 #[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=task_list))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
 pub struct TaskList {
     pub id: i32,
     pub title: String,
     pub project_id: i32,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TaskList {
+    pub fn by_id(conn: &mut PgConnection, task_list_id: i32) -> Option<TaskList> {
+        use crate::schema::task_list::dsl::*;
+        let res: Result<Vec<TaskList>, _> = task_list.filter(id.eq(task_list_id)).load(conn);
+        match res {
+            Ok(l) => {
+                if l.len() > 0 {
+                    Some(l[0].clone())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Insertable))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=task_list))]
+pub struct NewTaskList {
+    pub title: String,
+    pub project_id: i32,
+    /// Appended after the last list of the project when absent.
+    #[serde(default)]
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsChangeset, Identifiable))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=task_list))]
+pub struct PatchTaskList {
+    pub id: i32,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub position: Option<i32>,
+}
+
+impl PatchTaskList {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.position.is_none()
+    }
+
+    pub fn patch(&self, target: &mut TaskList) {
+        if let Some(ref title) = self.title {
+            target.title = title.clone();
+        }
+        if let Some(position) = self.position {
+            target.position = position;
+        }
+    }
+}
+
+/// New order of the task lists of a project, first id goes first.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct TaskListOrder {
+    pub project_id: i32,
+    pub task_list_ids: Vec<i32>,
 }
 
 
@@ -52,14 +146,31 @@ pub struct TaskList {
 pub struct AppUser {
     pub id: i32,
     pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Created by an invite, nobody has signed in as this user yet.
+    #[serde(skip)]
+    pub placeholder: bool,
+    /// An identity provider vouched for the email, accounts registered with a
+    /// password are unverified until their first sign-in through one.
+    #[serde(skip)]
+    pub email_verified: bool,
 }
 
 
 #[cfg(not(target_arch = "wasm32"))]
 impl AppUser {
+    /// Emails are stored and compared trimmed and lowercase, every path that
+    /// creates or looks up a user goes through here.
+    pub fn normalize_email(user_email: &str) -> String {
+        user_email.trim().to_lowercase()
+    }
+
     pub fn by_email(conn: &mut PgConnection, user_email: &String) -> Option<AppUser> {
         use crate::schema::app_user::dsl::*;
-        let res: Result<Vec<AppUser>, _> = app_user.filter(email.eq(user_email)).load(conn);
+        let res: Result<Vec<AppUser>, _> = app_user
+            .filter(email.eq(AppUser::normalize_email(user_email)))
+            .load(conn);
         match res {
             Ok(p) => {
                 if p.len() > 0 {
@@ -73,7 +184,7 @@ impl AppUser {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Insertable))]
 #[serde(rename_all(deserialize = "camelCase"))]
 #[serde(rename_all(serialize = "camelCase"))]
@@ -82,6 +193,9 @@ pub struct NewProject {
     pub title: String,
     //  pub id: i32,
     pub owner_id: i32,
+    /// Placed after the caller's other projects when absent.
+    #[serde(default)]
+    pub priority: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -90,25 +204,289 @@ pub struct NewProject {
 #[serde(rename_all(serialize = "camelCase"))]
 #[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=project))]
 pub struct PatchProject {
-    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     pub id: i32,
     //pub owner_id: i32,
-    //pub priority: i32
+    /// The version the changes were made to, the update fails with a conflict if it is stale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(not(target_arch = "wasm32"), diesel(skip_update))]
+    pub version: Option<i32>,
 }
 
 impl PatchProject {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+    }
+
     pub fn patch(&self, target: &mut Project) {
-        target.title = self.title.clone();
+        if let Some(ref title) = self.title {
+            target.title = title.clone();
+        }
     }
 }
 
+/// New order of the caller's projects, first id goes first.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ProjectOrder {
+    pub project_ids: Vec<i32>,
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, Serialize, Deserialize, Clone, Insertable)]
 #[table_name = "app_user"]
 pub struct NewAppUser {
     pub email: String,
+    pub placeholder: bool,
+    pub email_verified: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Identifiable, Queryable, Insertable)]
+#[diesel(table_name=password_credential)]
+#[diesel(primary_key(user_id))]
+pub struct PasswordCredential {
+    pub user_id: i32,
+    /// Argon2 hash in PHC string format.
+    pub password_hash: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PasswordCredential {
+    pub fn by_user_id(conn: &mut PgConnection, credential_user_id: i32) -> Option<PasswordCredential> {
+        use crate::schema::password_credential::dsl::*;
+        password_credential
+            .filter(user_id.eq(credential_user_id))
+            .first(conn)
+            .ok()
+    }
+}
+
+/// What a personal api token is allowed to do.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsExpression, FromSqlRow))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = Text))]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    ReadOnly,
+    #[default]
+    ReadWrite,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::ReadOnly => "read_only",
+            TokenScope::ReadWrite => "read_write",
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ToSql<Text, Pg> for TokenScope {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl FromSql<Text, Pg> for TokenScope {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"read_only" => Ok(TokenScope::ReadOnly),
+            b"read_write" => Ok(TokenScope::ReadWrite),
+            other => Err(format!("Unrecognized token scope: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/// Role of a user in a shared project.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsExpression, FromSqlRow))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = Text))]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectRole {
+    /// Manages members and may delete the project.
+    Owner,
+    /// Reads and changes tasks and task lists.
+    Editor,
+    /// Only reads.
+    Viewer,
+}
+
+impl ProjectRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectRole::Owner => "owner",
+            ProjectRole::Editor => "editor",
+            ProjectRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn can_write(&self) -> bool {
+        matches!(self, ProjectRole::Owner | ProjectRole::Editor)
+    }
+
+    pub fn can_manage(&self) -> bool {
+        matches!(self, ProjectRole::Owner)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ToSql<Text, Pg> for ProjectRole {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FromSql<Text, Pg> for ProjectRole {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"owner" => Ok(ProjectRole::Owner),
+            b"editor" => Ok(ProjectRole::Editor),
+            b"viewer" => Ok(ProjectRole::Viewer),
+            other => Err(format!("Unrecognized project role: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Identifiable, Associations, Queryable, Insertable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(Project)))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(AppUser, foreign_key = user_id)))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=project_member))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(primary_key(project_id, user_id)))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ProjectMember {
+    pub project_id: i32,
+    pub user_id: i32,
+    pub role: ProjectRole,
+    /// Where the project sits in this member's project list.
+    #[serde(default)]
+    pub position: i32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ProjectMember {
+    pub fn role_of(conn: &mut PgConnection, member_project_id: i32, member_user_id: i32) -> Option<ProjectRole> {
+        use crate::schema::project_member::dsl::*;
+        project_member
+            .filter(project_id.eq(member_project_id).and(user_id.eq(member_user_id)))
+            .select(role)
+            .first(conn)
+            .ok()
+    }
+
+    /// The position after the last project of the user.
+    pub fn next_position(conn: &mut PgConnection, member_user_id: i32) -> QueryResult<i32> {
+        use crate::schema::project_member::dsl::*;
+        let last: Option<i32> = project_member
+            .filter(user_id.eq(member_user_id))
+            .select(diesel::dsl::max(position))
+            .first(conn)?;
+        Ok(last.map_or(0, |p| p + 1))
+    }
+}
+
+/// A member of a project as listed by the api.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ProjectMemberInfo {
+    pub user_id: i32,
+    pub email: String,
+    pub role: ProjectRole,
+}
+
+/// Body of the request sharing a project with someone by email.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct InviteMember {
+    pub project_id: i32,
+    pub email: String,
+    pub role: ProjectRole,
+}
+
+/// Personal api token, the secret itself is only stored as a hash.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Identifiable, Queryable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=api_token))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ApiToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub scope: TokenScope,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ApiToken {
+    pub fn by_hash(conn: &mut PgConnection, hash: &str) -> Option<ApiToken> {
+        use crate::schema::api_token::dsl::*;
+        api_token.filter(token_hash.eq(hash)).first(conn).ok()
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Body of the request minting a new api token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct CreateApiToken {
+    pub name: String,
+    #[serde(default)]
+    pub scope: TokenScope,
+    /// The token never expires when absent.
+    #[serde(default)]
+    pub expires_in_days: Option<u32>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name=api_token)]
+pub struct NewApiToken {
+    pub user_id: i32,
+    pub name: String,
+    pub token_hash: String,
+    pub scope: TokenScope,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Returned once when a token is minted, the secret can not be read again.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct CreatedApiToken {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+/// Body of the email/password registration and login requests.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Credentials {
+    pub email: String,
+    pub password: String,
+}
+
+/// Upper bound for `Task::notes` in bytes.
+pub const MAX_NOTES_LEN: usize = 64 * 1024;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Insertable))]
 #[serde(rename_all(deserialize = "camelCase"))]
@@ -118,6 +496,15 @@ pub struct NewTask {
     pub title: String,
     pub project_id: i32,
     pub task_list_id: Option<i32>,
+    /// Appended after the last task of its task list when absent.
+    #[serde(default)]
+    pub position: Option<i32>,
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub notes: String,
 }
 
 
@@ -126,6 +513,7 @@ pub struct NewTask {
 #[cfg_attr(not(target_arch = "wasm32"), derive(Identifiable, Associations, Queryable))]
 #[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=task))]
 #[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(Project)))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(TaskList)))]
 #[serde(rename_all(deserialize = "camelCase"))]
 #[serde(rename_all(serialize = "camelCase"))]
 pub struct Task {
@@ -134,6 +522,336 @@ pub struct Task {
     pub task_list_id: Option<i32>,
     pub title: String,
     pub completed: bool,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub remind_at: Option<DateTime<Utc>>,
+    /// Markdown, at most `MAX_NOTES_LEN` bytes.
+    pub notes: String,
+    /// Number of checklist items, maintained by the database.
+    pub checklist_total: i32,
+    /// Number of checked checklist items.
+    pub checklist_done: i32,
+    /// When the task was moved to the trash.
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Counts the edits, served as the `ETag` of the task.
+    pub version: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsChangeset, Identifiable))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=task))]
+pub struct PatchTask {
+    pub id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<bool>,
+    /// `Some(None)` takes the task out of its task list.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option")]
+    pub task_list_id: Option<Option<i32>>,
+    /// `Some(None)` clears the due date.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    /// `Some(None)` clears the reminder.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option")]
+    pub remind_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// The version the changes were made to, the update fails with a conflict if it is stale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(not(target_arch = "wasm32"), diesel(skip_update))]
+    pub version: Option<i32>,
+}
+
+impl PatchTask {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.completed.is_none()
+            && self.task_list_id.is_none()
+            && self.due_at.is_none()
+            && self.remind_at.is_none()
+            && self.notes.is_none()
+    }
+
+    pub fn patch(&self, target: &mut Task) {
+        if let Some(ref title) = self.title {
+            target.title = title.clone();
+        }
+        if let Some(completed) = self.completed {
+            target.completed = completed;
+        }
+        if let Some(task_list_id) = self.task_list_id {
+            target.task_list_id = task_list_id;
+        }
+        if let Some(due_at) = self.due_at {
+            target.due_at = due_at;
+        }
+        if let Some(remind_at) = self.remind_at {
+            target.remind_at = remind_at;
+        }
+        if let Some(ref notes) = self.notes {
+            target.notes = notes.clone();
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Identifiable, Associations, Queryable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(Task)))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=checklist_item))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ChecklistItem {
+    pub id: i32,
+    pub task_id: i32,
+    pub title: String,
+    pub done: bool,
+    pub position: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Insertable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=checklist_item))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct NewChecklistItem {
+    pub task_id: i32,
+    pub title: String,
+    /// Appended after the last item of the task when absent.
+    #[serde(default)]
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsChangeset, Identifiable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=checklist_item))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct PatchChecklistItem {
+    pub id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done: Option<bool>,
+}
+
+impl PatchChecklistItem {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.done.is_none()
+    }
+}
+
+/// New order of the checklist of a task, first id goes first.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ChecklistOrder {
+    pub task_id: i32,
+    pub item_ids: Vec<i32>,
+}
+
+/// A task with its checklist, answered by the checklist endpoints so the
+/// rollup on the task stays in sync.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct Checklist {
+    pub task: Task,
+    pub items: Vec<ChecklistItem>,
+}
+
+/// Project wide label, `color` is a `#rrggbb` hex string.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Identifiable, Associations, Queryable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(Project)))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=label))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct Label {
+    pub id: i32,
+    pub project_id: i32,
+    pub name: String,
+    pub color: String,
+}
+
+impl Label {
+    pub fn rgb(&self) -> Option<[u8; 3]> {
+        parse_color(&self.color)
+    }
+}
+
+/// Parses a `#rrggbb` color.
+pub fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Insertable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=label))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct NewLabel {
+    pub project_id: i32,
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsChangeset, Identifiable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=label))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct PatchLabel {
+    pub id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl PatchLabel {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.color.is_none()
+    }
+}
+
+/// A label attached to a task.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Identifiable, Associations, Queryable, Insertable))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(Task)))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(belongs_to(Label)))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(table_name=task_label))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(primary_key(task_id, label_id)))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct TaskLabel {
+    pub task_id: i32,
+    pub label_id: i32,
+}
+
+/// What a search hit points at.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsExpression, FromSqlRow))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = Text))]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    Project,
+    TaskList,
+    Task,
+}
+
+impl SearchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchKind::Project => "project",
+            SearchKind::TaskList => "task_list",
+            SearchKind::Task => "task",
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ToSql<Text, Pg> for SearchKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FromSql<Text, Pg> for SearchKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"project" => Ok(SearchKind::Project),
+            b"task_list" => Ok(SearchKind::TaskList),
+            b"task" => Ok(SearchKind::Task),
+            other => Err(format!("Unrecognized search kind: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/// A project, task list or task whose title matches a search, best matches first.
+/// `snippet` is the title with each match between `MATCH_START` and `MATCH_END`,
+/// the server drops those characters from the title so they only mark matches.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(QueryableByName))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct SearchHit {
+    #[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = Text))]
+    pub kind: SearchKind,
+    #[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = diesel::sql_types::Integer))]
+    pub id: i32,
+    #[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = diesel::sql_types::Integer))]
+    pub project_id: i32,
+    #[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = Text))]
+    pub title: String,
+    #[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = Text))]
+    pub snippet: String,
+    #[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = diesel::sql_types::Float))]
+    pub rank: f32,
+}
+
+impl SearchHit {
+    /// Marks the start of a match in `snippet`. Control characters, so the
+    /// snippet is never mistaken for markup.
+    pub const MATCH_START: char = '\u{2}';
+    /// Marks the end of a match in `snippet`.
+    pub const MATCH_END: char = '\u{3}';
+
+    /// Splits the snippet into `(text, highlighted)` parts, the sentinels
+    /// themselves are left out.
+    pub fn snippet_parts(&self) -> Vec<(&str, bool)> {
+        let mut parts = Vec::new();
+        let mut highlighted = false;
+        let mut rest = self.snippet.as_str();
+        while let Some(at) = rest.find([SearchHit::MATCH_START, SearchHit::MATCH_END]) {
+            if at > 0 {
+                parts.push((&rest[..at], highlighted));
+            }
+            highlighted = rest[at..].starts_with(SearchHit::MATCH_START);
+            // both sentinels are a single byte
+            rest = &rest[at + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push((rest, highlighted));
+        }
+        parts
+    }
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field (`None`).
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    serde::Deserialize::deserialize(deserializer).map(Some)
+}
+
+/// Moves a task right before or after another task of the same project,
+/// or to the end of a task list when neither is given.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct MoveTask {
+    pub id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<i32>,
+    /// Target list without an anchor task, `None` moves the task out of its list.
+    #[serde(default)]
+    pub task_list_id: Option<i32>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -153,3 +871,231 @@ impl Task {
         }
     }
 }
+
+/// Json body of every error response of the api.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ErrorResponse {
+    pub code: String,
+    pub message: String,
+}
+
+/// One page of a list endpoint, pass `next_cursor` as `cursor` to fetch the next page.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct ListResponse<T> {
+    pub items: Vec<T>,
+    /// `None` on the last page.
+    #[serde(default)]
+    pub next_cursor: Option<Cursor>,
+}
+
+/// Trashed projects and the trashed tasks of live projects, newest first.
+/// Tasks of a trashed project come back with their project.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Trash {
+    pub projects: Vec<Project>,
+    pub tasks: Vec<Task>,
+}
+
+/// Names a trashed project or task, `{"project": 1}` or `{"task": 2}`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashItem {
+    Project(i32),
+    Task(i32),
+}
+
+/// What an activity is about.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsExpression, FromSqlRow))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = Text))]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityEntity {
+    Project,
+    TaskList,
+    Task,
+    ChecklistItem,
+    Label,
+    /// A project member, keyed by their user id.
+    Member,
+}
+
+impl ActivityEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityEntity::Project => "project",
+            ActivityEntity::TaskList => "task_list",
+            ActivityEntity::Task => "task",
+            ActivityEntity::ChecklistItem => "checklist_item",
+            ActivityEntity::Label => "label",
+            ActivityEntity::Member => "member",
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ToSql<Text, Pg> for ActivityEntity {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FromSql<Text, Pg> for ActivityEntity {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"project" => Ok(ActivityEntity::Project),
+            b"task_list" => Ok(ActivityEntity::TaskList),
+            b"task" => Ok(ActivityEntity::Task),
+            b"checklist_item" => Ok(ActivityEntity::ChecklistItem),
+            b"label" => Ok(ActivityEntity::Label),
+            b"member" => Ok(ActivityEntity::Member),
+            other => Err(format!("Unrecognized activity entity: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/// What happened to the entity of an activity.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(AsExpression, FromSqlRow))]
+#[cfg_attr(not(target_arch = "wasm32"), diesel(sql_type = Text))]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityAction {
+    Create,
+    Update,
+    /// Moved to the trash for projects and tasks, deleted for good otherwise.
+    Delete,
+    Restore,
+    Move,
+    Reorder,
+    /// A label was attached to a task.
+    Attach,
+    Detach,
+}
+
+impl ActivityAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityAction::Create => "create",
+            ActivityAction::Update => "update",
+            ActivityAction::Delete => "delete",
+            ActivityAction::Restore => "restore",
+            ActivityAction::Move => "move",
+            ActivityAction::Reorder => "reorder",
+            ActivityAction::Attach => "attach",
+            ActivityAction::Detach => "detach",
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ToSql<Text, Pg> for ActivityAction {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FromSql<Text, Pg> for ActivityAction {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"create" => Ok(ActivityAction::Create),
+            b"update" => Ok(ActivityAction::Update),
+            b"delete" => Ok(ActivityAction::Delete),
+            b"restore" => Ok(ActivityAction::Restore),
+            b"move" => Ok(ActivityAction::Move),
+            b"reorder" => Ok(ActivityAction::Reorder),
+            b"attach" => Ok(ActivityAction::Attach),
+            b"detach" => Ok(ActivityAction::Detach),
+            other => Err(format!("Unrecognized activity action: {}", String::from_utf8_lossy(other)).into()),
+        }
+    }
+}
+
+/// One recorded change in a project. `diff` holds the created or deleted
+/// entity, `{"field": {"from": .., "to": ..}}` for updates.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Queryable))]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct Activity {
+    pub id: i32,
+    pub project_id: i32,
+    pub actor_id: i32,
+    pub entity_type: ActivityEntity,
+    pub entity_id: i32,
+    pub action: ActivityAction,
+    pub diff: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    /// Email of the actor.
+    pub actor_email: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name=activity)]
+pub struct NewActivity {
+    pub project_id: i32,
+    pub actor_id: i32,
+    pub entity_type: ActivityEntity,
+    pub entity_id: i32,
+    pub action: ActivityAction,
+    pub diff: serde_json::Value,
+}
+
+/// A change pushed over `GET /api/events` to the members of the project it
+/// happened in, written as `{"type": "task_changed", "data": {..}}`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    /// A project was created, changed or restored from the trash.
+    ProjectChanged(Project),
+    /// A project was moved to the trash.
+    ProjectDeleted(Project),
+    /// A task was created, changed or restored from the trash.
+    TaskChanged(Task),
+    /// A task was moved to the trash.
+    TaskDeleted(Task),
+    /// All tasks of a project in their new order after one of them was moved.
+    TasksMoved(Vec<Task>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(snippet: &str) -> SearchHit {
+        SearchHit {
+            kind: SearchKind::Task,
+            id: 1,
+            project_id: 1,
+            title: String::new(),
+            snippet: snippet.to_owned(),
+            rank: 0.0,
+        }
+    }
+
+    #[test]
+    fn snippet_parts_follow_the_match_markers() {
+        let h = hit("buy \u{2}milk\u{3} and \u{2}bread\u{3}");
+        assert_eq!(h.snippet_parts(), vec![("buy ", false), ("milk", true), (" and ", false), ("bread", true)]);
+        assert_eq!(hit("\u{2}all\u{3}").snippet_parts(), vec![("all", true)]);
+        assert_eq!(hit("").snippet_parts(), vec![]);
+    }
+
+    #[test]
+    fn snippet_parts_drop_stray_markers() {
+        assert_eq!(hit("a\u{3}b").snippet_parts(), vec![("a", false), ("b", false)]);
+        assert_eq!(hit("a\u{2}b").snippet_parts(), vec![("a", false), ("b", true)]);
+        assert_eq!(hit("\u{2}\u{3}\u{3}").snippet_parts(), vec![]);
+    }
+
+    #[test]
+    fn snippet_markup_is_plain_text() {
+        let h = hit("<b>\u{2}x\u{3}</b>");
+        assert_eq!(h.snippet_parts(), vec![("<b>", false), ("x", true), ("</b>", false)]);
+    }
+}
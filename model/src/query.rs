@@ -0,0 +1,242 @@
+//! Typed query strings of the list endpoints and their keyset cursors, read by
+//! the backend and written by its clients.
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+/// Page size when the caller does not pass `limit`.
+pub const DEFAULT_LIMIT: i64 = 50;
+/// Largest accepted `limit`.
+pub const MAX_LIMIT: i64 = 500;
+
+/// Order requested with the `sort` query parameter of the list endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SortBy {
+    /// The user defined order, priority for projects and position otherwise.
+    #[default]
+    Manual,
+    CreatedAt { desc: bool },
+    UpdatedAt { desc: bool },
+}
+
+impl SortBy {
+    pub fn is_manual(&self) -> bool {
+        *self == SortBy::Manual
+    }
+}
+
+/// Parses `createdAt` or `updatedAt` with a leading `-` for newest first,
+/// `position` and `priority` stand for the manual order.
+impl TryFrom<String> for SortBy {
+    type Error = String;
+
+    fn try_from(sort: String) -> Result<Self, Self::Error> {
+        let (key, desc) = match sort.strip_prefix('-') {
+            Some(key) => (key, true),
+            None => (sort.as_str(), false),
+        };
+        match key {
+            "position" | "priority" => Ok(SortBy::Manual),
+            "createdAt" => Ok(SortBy::CreatedAt { desc }),
+            "updatedAt" => Ok(SortBy::UpdatedAt { desc }),
+            _ => Err(format!("unknown sort `{}`, expected `createdAt` or `updatedAt`", sort)),
+        }
+    }
+}
+
+impl From<SortBy> for String {
+    fn from(sort: SortBy) -> String {
+        let (key, desc) = match sort {
+            SortBy::Manual => ("position", false),
+            SortBy::CreatedAt { desc } => ("createdAt", desc),
+            SortBy::UpdatedAt { desc } => ("updatedAt", desc),
+        };
+        if desc { format!("-{}", key) } else { key.to_owned() }
+    }
+}
+
+/// Due date filter of `GET /api/task`, from the `due` query parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DueFilter {
+    /// Open tasks whose due date has passed.
+    Overdue,
+    Today,
+    /// Monday to Sunday of the current week.
+    #[serde(rename = "week")]
+    ThisWeek,
+}
+
+/// Where the previous page ended: the sort key of its last row and the row's id,
+/// written as `<key>.<id>`. Timestamps are keyed by their microseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cursor {
+    pub key: i64,
+    pub id: i32,
+}
+
+impl Cursor {
+    pub fn at_position(position: i32, id: i32) -> Cursor {
+        Cursor { key: position as i64, id }
+    }
+
+    pub fn at_time(time: DateTime<Utc>, id: i32) -> Cursor {
+        Cursor { key: time.timestamp_micros(), id }
+    }
+
+    /// The key as a position, `None` if the cursor came from another sort.
+    pub fn position(&self) -> Option<i32> {
+        i32::try_from(self.key).ok()
+    }
+
+    /// The key as a timestamp, `None` if it is out of range.
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        let secs = self.key.div_euclid(1_000_000);
+        let micros = self.key.rem_euclid(1_000_000) as u32;
+        DateTime::from_timestamp(secs, micros * 1000)
+    }
+}
+
+impl TryFrom<String> for Cursor {
+    type Error = String;
+
+    fn try_from(cursor: String) -> Result<Self, Self::Error> {
+        cursor
+            .rsplit_once('.')
+            .and_then(|(key, id)| Some(Cursor { key: key.parse().ok()?, id: id.parse().ok()? }))
+            .ok_or_else(|| format!("invalid cursor `{}`", cursor))
+    }
+}
+
+impl From<Cursor> for String {
+    fn from(cursor: Cursor) -> String {
+        cursor.to_string()
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.key, self.id)
+    }
+}
+
+fn is_zero(n: &i32) -> bool {
+    *n == 0
+}
+
+/// `GET /api/task`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskQuery {
+    /// Required unless `due` is given, due date views span all of the caller's projects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<i32>,
+    /// Only tasks carrying this label.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<DueFilter>,
+    /// The caller's offset from UTC in minutes, decides where the days of `due` start.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub utc_offset: i32,
+    #[serde(default, skip_serializing_if = "SortBy::is_manual")]
+    pub sort: SortBy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Cursor>,
+}
+
+impl TaskQuery {
+    /// The first page of a project's tasks in their manual order.
+    pub fn project(project_id: i32) -> TaskQuery {
+        TaskQuery { project_id: Some(project_id), ..Default::default() }
+    }
+}
+
+/// `GET /api/project`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectQuery {
+    #[serde(default, skip_serializing_if = "SortBy::is_manual")]
+    pub sort: SortBy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Cursor>,
+}
+
+/// `GET /api/task_list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskListQuery {
+    pub project_id: i32,
+    #[serde(default, skip_serializing_if = "SortBy::is_manual")]
+    pub sort: SortBy,
+}
+
+/// `GET /api/project/{id}/activity`, newest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActivityQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Cursor>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(s: &str) -> Result<Cursor, String> {
+        Cursor::try_from(s.to_owned())
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let c = Cursor { key: -1_500_000, id: 42 };
+        assert_eq!(c.to_string(), "-1500000.42");
+        assert_eq!(cursor(&c.to_string()), Ok(c));
+        assert_eq!(serde_json::to_string(&c).unwrap(), "\"-1500000.42\"");
+        assert_eq!(serde_json::from_str::<Cursor>("\"7.3\"").unwrap(), Cursor { key: 7, id: 3 });
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        for bad in ["", "12", "12.", ".3", "a.3", "1.b", "1.2.3", "1.99999999999"] {
+            assert!(cursor(bad).is_err(), "{:?} was accepted", bad);
+        }
+    }
+
+    #[test]
+    fn sort_parses_keys_and_direction() {
+        let sort = |s: &str| SortBy::try_from(s.to_owned());
+        assert_eq!(sort("position"), Ok(SortBy::Manual));
+        assert_eq!(sort("priority"), Ok(SortBy::Manual));
+        assert_eq!(sort("createdAt"), Ok(SortBy::CreatedAt { desc: false }));
+        assert_eq!(sort("-updatedAt"), Ok(SortBy::UpdatedAt { desc: true }));
+        for bad in ["", "-", "created_at", "--createdAt", "title"] {
+            assert!(sort(bad).is_err(), "{:?} was accepted", bad);
+        }
+    }
+
+    #[test]
+    fn sort_round_trips() {
+        for sort in [SortBy::Manual, SortBy::CreatedAt { desc: true }, SortBy::UpdatedAt { desc: false }] {
+            let json = serde_json::to_string(&sort).unwrap();
+            assert_eq!(serde_json::from_str::<SortBy>(&json).unwrap(), sort, "{}", json);
+        }
+    }
+
+    #[test]
+    fn queries_leave_defaults_out() {
+        let query = TaskQuery { due: Some(DueFilter::ThisWeek), cursor: Some(Cursor { key: 3, id: 4 }), ..TaskQuery::project(2) };
+        assert_eq!(serde_json::to_string(&query).unwrap(), r#"{"projectId":2,"due":"week","cursor":"3.4"}"#);
+        let sorted = ProjectQuery { sort: SortBy::CreatedAt { desc: true }, ..Default::default() };
+        assert_eq!(serde_json::to_string(&sorted).unwrap(), r#"{"sort":"-createdAt"}"#);
+    }
+}
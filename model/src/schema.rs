@@ -1,9 +1,65 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    activity (id) {
+        id -> Int4,
+        project_id -> Int4,
+        actor_id -> Int4,
+        entity_type -> Varchar,
+        entity_id -> Int4,
+        action -> Varchar,
+        diff -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    api_token (id) {
+        id -> Int4,
+        user_id -> Int4,
+        name -> Varchar,
+        token_hash -> Varchar,
+        scope -> Varchar,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     app_user (id) {
         id -> Int4,
         email -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        placeholder -> Bool,
+        email_verified -> Bool,
+    }
+}
+
+diesel::table! {
+    checklist_item (id) {
+        id -> Int4,
+        task_id -> Int4,
+        title -> Varchar,
+        done -> Bool,
+        position -> Int4,
+    }
+}
+
+diesel::table! {
+    label (id) {
+        id -> Int4,
+        project_id -> Int4,
+        name -> Varchar,
+        color -> Varchar,
+    }
+}
+
+diesel::table! {
+    password_credential (user_id) {
+        user_id -> Int4,
+        password_hash -> Varchar,
     }
 }
 
@@ -13,6 +69,19 @@ diesel::table! {
         owner_id -> Int4,
         title -> Varchar,
         priority -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+        version -> Int4,
+    }
+}
+
+diesel::table! {
+    project_member (project_id, user_id) {
+        project_id -> Int4,
+        user_id -> Int4,
+        role -> Varchar,
+        position -> Int4,
     }
 }
 
@@ -23,6 +92,23 @@ diesel::table! {
         task_list_id -> Nullable<Int4>,
         title -> Varchar,
         completed -> Bool,
+        position -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        due_at -> Nullable<Timestamptz>,
+        remind_at -> Nullable<Timestamptz>,
+        notes -> Text,
+        checklist_total -> Int4,
+        checklist_done -> Int4,
+        deleted_at -> Nullable<Timestamptz>,
+        version -> Int4,
+    }
+}
+
+diesel::table! {
+    task_label (task_id, label_id) {
+        task_id -> Int4,
+        label_id -> Int4,
     }
 }
 
@@ -31,14 +117,36 @@ diesel::table! {
         id -> Int4,
         title -> Varchar,
         project_id -> Int4,
+        position -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
+diesel::joinable!(activity -> app_user (actor_id));
+diesel::joinable!(activity -> project (project_id));
+diesel::joinable!(api_token -> app_user (user_id));
+diesel::joinable!(checklist_item -> task (task_id));
+diesel::joinable!(label -> project (project_id));
+diesel::joinable!(password_credential -> app_user (user_id));
+diesel::joinable!(project_member -> app_user (user_id));
+diesel::joinable!(project_member -> project (project_id));
 diesel::joinable!(task -> project (project_id));
+diesel::joinable!(task -> task_list (task_list_id));
+diesel::joinable!(task_label -> label (label_id));
+diesel::joinable!(task_label -> task (task_id));
+diesel::joinable!(task_list -> project (project_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    activity,
+    api_token,
     app_user,
+    checklist_item,
+    label,
+    password_credential,
     project,
+    project_member,
     task,
+    task_label,
     task_list,
 );
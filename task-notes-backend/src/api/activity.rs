@@ -0,0 +1,114 @@
+use crate::api::query::{cursor_key, page, page_limit, ActivityQuery, Cursor};
+use crate::api::{check_project_access, Access, Pool};
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use model::models::{Activity, ActivityAction, ActivityEntity, NewActivity};
+use model::schema::{activity, app_user};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Records a change of a project, call it inside the transaction of the change.
+pub fn record(
+    conn: &mut PgConnection,
+    actor_id: i32,
+    project_id: i32,
+    entity: (ActivityEntity, i32),
+    action: ActivityAction,
+    diff: Value,
+) -> Result<(), ApiError> {
+    diesel::insert_into(activity::table)
+        .values(&NewActivity {
+            project_id,
+            actor_id,
+            entity_type: entity.0,
+            entity_id: entity.1,
+            action,
+            diff,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Records a created, deleted or restored entity with all of its fields.
+pub fn record_entity<T: Serialize>(
+    conn: &mut PgConnection,
+    actor_id: i32,
+    project_id: i32,
+    entity: (ActivityEntity, i32),
+    action: ActivityAction,
+    value: &T,
+) -> Result<(), ApiError> {
+    record(conn, actor_id, project_id, entity, action, to_json(value)?)
+}
+
+/// Records the fields that differ between `before` and `after`, nothing if none do.
+pub fn record_changes<T: Serialize>(
+    conn: &mut PgConnection,
+    actor_id: i32,
+    project_id: i32,
+    entity: (ActivityEntity, i32),
+    action: ActivityAction,
+    before: &T,
+    after: &T,
+) -> Result<(), ApiError> {
+    let changes = diff(&to_json(before)?, &to_json(after)?);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    record(conn, actor_id, project_id, entity, action, Value::Object(changes))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value, ApiError> {
+    serde_json::to_value(value).map_err(|e| ApiError::Internal(e.to_string()))
+}
+
+/// `{"field": {"from": .., "to": ..}}` for every top level field that changed,
+/// the bookkeeping timestamps and versions aside.
+fn diff(before: &Value, after: &Value) -> Map<String, Value> {
+    let mut changes = Map::new();
+    if let (Value::Object(before), Value::Object(after)) = (before, after) {
+        for (key, to) in after {
+            if matches!(key.as_str(), "updatedAt" | "updated_at" | "version") {
+                continue;
+            }
+            let from = before.get(key).unwrap_or(&Value::Null);
+            if from != to {
+                changes.insert(key.clone(), json!({ "from": from, "to": to }));
+            }
+        }
+    }
+    changes
+}
+
+/// Lists the recorded changes of a project one page at a time, newest first.
+pub async fn get_activity(
+    path: web::Path<i32>,
+    query: web::Query<ActivityQuery>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let pid = path.into_inner();
+    let limit = page_limit(query.limit).map_err(ApiError::Validation)?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    check_project_access(conn, user.id, pid, Access::Read)?;
+    let entries = activity::table
+        .inner_join(app_user::table)
+        .filter(activity::project_id.eq(pid))
+        .select((
+            activity::id,
+            activity::project_id,
+            activity::actor_id,
+            activity::entity_type,
+            activity::entity_id,
+            activity::action,
+            activity::diff,
+            activity::created_at,
+            app_user::email,
+        ))
+        .into_boxed();
+    let entries = keyset!(entries, activity::created_at, activity::id, true, cursor_key(query.cursor, Cursor::time)?);
+    let entries = entries.limit(limit + 1).load::<Activity>(conn)?;
+    Ok(HttpResponse::Ok().json(page(entries, limit, |a| Cursor::at_time(a.created_at, a.id))))
+}
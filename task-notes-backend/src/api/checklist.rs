@@ -0,0 +1,167 @@
+use crate::api::activity::{record, record_changes, record_entity};
+use crate::api::events::Broadcaster;
+use crate::api::{check_project_access, check_task_access, query_id, Access, Pool};
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use model::models::{self, ActivityAction, ActivityEntity, ChangeEvent, Checklist, ChecklistItem, ChecklistOrder, NewChecklistItem, PatchChecklistItem};
+use model::schema::{checklist_item, task};
+use std::collections::HashMap;
+
+/// The task with its items in checklist order.
+fn load_checklist(conn: &mut PgConnection, tid: i32) -> Result<Checklist, ApiError> {
+    let task: models::Task = task::table.find(tid).first(conn)?;
+    let items = checklist_item::table
+        .filter(checklist_item::task_id.eq(tid))
+        .order((checklist_item::position.asc(), checklist_item::id.asc()))
+        .load::<ChecklistItem>(conn)?;
+    Ok(Checklist { task, items })
+}
+
+/// Sends the task with its updated checklist rollup to the project's members.
+fn publish_rollup(conn: &mut PgConnection, broadcaster: &Broadcaster, checklist: &Checklist) {
+    let event = ChangeEvent::TaskChanged(checklist.task.clone());
+    broadcaster.publish(conn, checklist.task.project_id, &event);
+}
+
+/// Returns the item and its task if the caller's role in its project allows `access`.
+fn check_item_access(
+    conn: &mut PgConnection,
+    uid: i32,
+    iid: i32,
+    access: Access,
+) -> Result<(ChecklistItem, models::Task), ApiError> {
+    let item: ChecklistItem = checklist_item::table.find(iid).first(conn)?;
+    let task = check_task_access(conn, uid, item.task_id, access)?;
+    Ok((item, task))
+}
+
+/// Lists the checklist items of all live tasks in a project, trashed tasks keep theirs.
+pub async fn get_checklist_items(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let pid: i32 = query_id(&query, "projectId")?;
+    check_project_access(conn, user.id, pid, Access::Read)?;
+    let items = checklist_item::table
+        .inner_join(task::table)
+        .filter(task::project_id.eq(pid))
+        .filter(task::deleted_at.is_null())
+        .select(checklist_item::all_columns)
+        .order((
+            checklist_item::task_id.asc(),
+            checklist_item::position.asc(),
+            checklist_item::id.asc(),
+        ))
+        .load::<ChecklistItem>(conn)?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+pub async fn create_checklist_item(
+    item: web::Json<NewChecklistItem>,
+    pool: web::Data<Pool>,
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    if item.title.trim().is_empty() {
+        return Err(ApiError::Validation("a checklist item needs a title".to_owned()));
+    }
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let t = check_task_access(conn, user.id, item.task_id, Access::Write)?;
+    let mut new_item = item.into_inner();
+    if new_item.position.is_none() {
+        let last: Option<i32> = checklist_item::table
+            .filter(checklist_item::task_id.eq(new_item.task_id))
+            .select(diesel::dsl::max(checklist_item::position))
+            .first(conn)?;
+        new_item.position = Some(last.map_or(0, |p| p + 1));
+    }
+    let checklist = conn.transaction::<Checklist, ApiError, _>(|conn| {
+        let res: ChecklistItem = diesel::insert_into(checklist_item::table)
+            .values(&new_item)
+            .get_result(conn)?;
+        let entity = (ActivityEntity::ChecklistItem, res.id);
+        record_entity(conn, user.id, t.project_id, entity, ActivityAction::Create, &res)?;
+        load_checklist(conn, res.task_id)
+    })?;
+    publish_rollup(conn, &broadcaster, &checklist);
+    Ok(HttpResponse::Ok().json(checklist))
+}
+
+/// Renames or toggles an item.
+pub async fn update_checklist_item(
+    item: web::Json<PatchChecklistItem>,
+    pool: web::Data<Pool>,
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let (current, t) = check_item_access(conn, user.id, item.id, Access::Write)?;
+    let patch = item.into_inner();
+    let checklist = conn.transaction::<Checklist, ApiError, _>(|conn| {
+        if !patch.is_empty() {
+            let res: ChecklistItem = diesel::update(&patch).set(&patch).get_result(conn)?;
+            let entity = (ActivityEntity::ChecklistItem, res.id);
+            record_changes(conn, user.id, t.project_id, entity, ActivityAction::Update, &current, &res)?;
+        }
+        load_checklist(conn, current.task_id)
+    })?;
+    publish_rollup(conn, &broadcaster, &checklist);
+    Ok(HttpResponse::Ok().json(checklist))
+}
+
+pub async fn delete_checklist_item(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let iid: i32 = query_id(&query, "id")?;
+    let (current, t) = check_item_access(conn, user.id, iid, Access::Write)?;
+    let checklist = conn.transaction::<Checklist, ApiError, _>(|conn| {
+        diesel::delete(checklist_item::table.find(iid)).execute(conn)?;
+        let entity = (ActivityEntity::ChecklistItem, iid);
+        record_entity(conn, user.id, t.project_id, entity, ActivityAction::Delete, &current)?;
+        load_checklist(conn, current.task_id)
+    })?;
+    publish_rollup(conn, &broadcaster, &checklist);
+    Ok(HttpResponse::Ok().json(checklist))
+}
+
+/// Rewrites the positions of a task's checklist in one transaction.
+pub async fn reorder_checklist(
+    order: web::Json<ChecklistOrder>,
+    pool: web::Data<Pool>,
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::checklist_item::dsl::*;
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let t = check_task_access(conn, user.id, order.task_id, Access::Write)?;
+    let checklist = conn.transaction::<Checklist, ApiError, _>(|conn| {
+        for (i, iid) in order.item_ids.iter().enumerate() {
+            let updated = diesel::update(checklist_item.filter(id.eq(iid).and(task_id.eq(order.task_id))))
+                .set(position.eq(i as i32))
+                .execute(conn)?;
+            if updated == 0 {
+                return Err(ApiError::Validation(format!(
+                    "checklist item {} does not belong to task {}",
+                    iid, order.task_id
+                )));
+            }
+        }
+        let diff = serde_json::json!({ "itemIds": order.item_ids });
+        record(conn, user.id, t.project_id, (ActivityEntity::Task, t.id), ActivityAction::Reorder, diff)?;
+        load_checklist(conn, order.task_id)
+    })?;
+    publish_rollup(conn, &broadcaster, &checklist);
+    Ok(HttpResponse::Ok().json(checklist))
+}
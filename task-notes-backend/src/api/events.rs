@@ -0,0 +1,97 @@
+use crate::auth::AuthUser;
+use actix_web::http::header;
+use actix_web::web::{self, Bytes};
+use actix_web::HttpResponse;
+use diesel::prelude::*;
+use futures::channel::mpsc;
+use futures::StreamExt;
+use model::models::ChangeEvent;
+use model::schema::project_member;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Keeps idle connections open through proxies and notices clients that went away.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// Messages a client may fall behind by before it is dropped.
+const CLIENT_BUFFER: usize = 64;
+
+struct Client {
+    user_id: i32,
+    sender: mpsc::Sender<Bytes>,
+}
+
+/// Fans committed changes out to the connected event streams of the members
+/// of the project they happened in.
+#[derive(Default)]
+pub struct Broadcaster {
+    clients: Mutex<Vec<Client>>,
+}
+
+impl Broadcaster {
+    /// A broadcaster with its keepalive running for the lifetime of the server.
+    pub fn create() -> Arc<Broadcaster> {
+        let broadcaster = Arc::new(Broadcaster::default());
+        actix_web::rt::spawn(broadcaster.clone().keepalive());
+        broadcaster
+    }
+
+    async fn keepalive(self: Arc<Self>) {
+        let mut interval = actix_web::rt::time::interval(KEEPALIVE_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.send(|_| true, Bytes::from_static(b": keepalive\n\n"));
+        }
+    }
+
+    fn subscribe(&self, user_id: i32) -> mpsc::Receiver<Bytes> {
+        let (mut sender, receiver) = mpsc::channel(CLIENT_BUFFER);
+        // answers the request right away, the stream would stay pending until the first event otherwise
+        let _ = sender.try_send(Bytes::from_static(b": connected\n\n"));
+        self.clients.lock().unwrap().push(Client { user_id, sender });
+        receiver
+    }
+
+    /// Sends `message` to the clients of the users `to` picks, drops the ones
+    /// that disconnected or can not keep up.
+    fn send(&self, to: impl Fn(i32) -> bool, message: Bytes) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|c| !to(c.user_id) || c.sender.try_send(message.clone()).is_ok());
+    }
+
+    /// Pushes `event` to the members of `project_id`, call it once the change is committed.
+    /// The change stands if this fails, clients pick it up on their next reload.
+    pub fn publish(&self, conn: &mut PgConnection, project_id: i32, event: &ChangeEvent) {
+        let members = project_member::table
+            .filter(project_member::project_id.eq(project_id))
+            .select(project_member::user_id)
+            .load::<i32>(conn);
+        let members = match members {
+            Ok(members) => members,
+            Err(e) => {
+                log::warn!("Loading the members of project {} failed: {}", project_id, e);
+                return;
+            }
+        };
+        let data = match serde_json::to_string(event) {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("Serializing a change event failed: {}", e);
+                return;
+            }
+        };
+        self.send(|uid| members.contains(&uid), Bytes::from(format!("data: {}\n\n", data)));
+    }
+}
+
+/// `GET /api/events`, a `text/event-stream` of the changes to the caller's projects.
+/// Every message carries one `ChangeEvent` as json.
+pub async fn events(broadcaster: web::Data<Broadcaster>, user: AuthUser) -> HttpResponse {
+    let receiver = broadcaster.subscribe(user.id);
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(receiver.map(Ok::<_, Infallible>))
+}
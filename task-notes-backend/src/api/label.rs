@@ -0,0 +1,165 @@
+use crate::api::activity::{record, record_changes, record_entity};
+use crate::api::{check_project_access, check_task_access, query_id, Access, Pool};
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use model::models::{parse_color, ActivityAction, ActivityEntity, Label, NewLabel, PatchLabel, TaskLabel};
+use model::schema::{label, task, task_label};
+use std::collections::HashMap;
+
+fn check_label(name: Option<&str>, color: Option<&str>) -> Result<(), ApiError> {
+    if name.is_some_and(|n| n.trim().is_empty()) {
+        return Err(ApiError::Validation("a label needs a name".to_owned()));
+    }
+    if let Some(color) = color {
+        if parse_color(color).is_none() {
+            return Err(ApiError::Validation(format!("`{}` is not a #rrggbb color", color)));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the label if the caller's role in its project allows `access`.
+fn check_label_access(conn: &mut PgConnection, uid: i32, lid: i32, access: Access) -> Result<Label, ApiError> {
+    let l: Label = label::table.find(lid).first(conn)?;
+    check_project_access(conn, uid, l.project_id, access)?;
+    Ok(l)
+}
+
+pub async fn get_labels(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let pid: i32 = query_id(&query, "projectId")?;
+    check_project_access(conn, user.id, pid, Access::Read)?;
+    let labels = label::table
+        .filter(label::project_id.eq(pid))
+        .order(label::name.asc())
+        .load::<Label>(conn)?;
+    Ok(HttpResponse::Ok().json(labels))
+}
+
+pub async fn create_label(
+    new_label: web::Json<NewLabel>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    check_label(Some(&new_label.name), Some(&new_label.color))?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    check_project_access(conn, user.id, new_label.project_id, Access::Write)?;
+    let res = conn.transaction::<Label, ApiError, _>(|conn| {
+        let res: Label = diesel::insert_into(label::table)
+            .values(&new_label.into_inner())
+            .get_result(conn)?;
+        record_entity(conn, user.id, res.project_id, (ActivityEntity::Label, res.id), ActivityAction::Create, &res)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Renames or recolors a label.
+pub async fn update_label(
+    l: web::Json<PatchLabel>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    check_label(l.name.as_deref(), l.color.as_deref())?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let current = check_label_access(conn, user.id, l.id, Access::Write)?;
+    if l.is_empty() {
+        return Ok(HttpResponse::Ok().json(current));
+    }
+    let res = conn.transaction::<Label, ApiError, _>(|conn| {
+        let res: Label = diesel::update(&l.clone()).set(l.into_inner()).get_result(conn)?;
+        let entity = (ActivityEntity::Label, res.id);
+        record_changes(conn, user.id, res.project_id, entity, ActivityAction::Update, &current, &res)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Deletes a label, it is detached from all tasks.
+pub async fn delete_label(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let lid: i32 = query_id(&query, "id")?;
+    check_label_access(conn, user.id, lid, Access::Write)?;
+    let res = conn.transaction::<Label, ApiError, _>(|conn| {
+        let res: Label = diesel::delete(label::table.find(lid)).get_result(conn)?;
+        record_entity(conn, user.id, res.project_id, (ActivityEntity::Label, lid), ActivityAction::Delete, &res)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Lists which labels are attached to the tasks of a project.
+pub async fn get_task_labels(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let pid: i32 = query_id(&query, "projectId")?;
+    check_project_access(conn, user.id, pid, Access::Read)?;
+    let attached = task_label::table
+        .inner_join(task::table)
+        .filter(task::project_id.eq(pid))
+        .select(task_label::all_columns)
+        .load::<TaskLabel>(conn)?;
+    Ok(HttpResponse::Ok().json(attached))
+}
+
+pub async fn attach_label(
+    attach: web::Json<TaskLabel>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let t = check_task_access(conn, user.id, attach.task_id, Access::Write)?;
+    let l: Label = label::table.find(attach.label_id).first(conn)?;
+    if l.project_id != t.project_id {
+        return Err(ApiError::Validation(format!(
+            "label {} does not belong to project {}",
+            l.id, t.project_id
+        )));
+    }
+    let res = conn.transaction::<TaskLabel, ApiError, _>(|conn| {
+        let res: TaskLabel = diesel::insert_into(task_label::table)
+            .values(&attach.into_inner())
+            .get_result(conn)?;
+        let diff = serde_json::json!({ "labelId": l.id, "name": l.name });
+        record(conn, user.id, t.project_id, (ActivityEntity::Task, t.id), ActivityAction::Attach, diff)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn detach_label(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let tid: i32 = query_id(&query, "taskId")?;
+    let lid: i32 = query_id(&query, "labelId")?;
+    let t = check_task_access(conn, user.id, tid, Access::Write)?;
+    let res = conn.transaction::<TaskLabel, ApiError, _>(|conn| {
+        let res: TaskLabel = diesel::delete(task_label::table.find((tid, lid))).get_result(conn)?;
+        let l: Label = label::table.find(lid).first(conn)?;
+        let diff = serde_json::json!({ "labelId": l.id, "name": l.name });
+        record(conn, user.id, t.project_id, (ActivityEntity::Task, tid), ActivityAction::Detach, diff)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,142 @@
+use crate::api::activity::{record_changes, record_entity};
+use crate::api::{check_project_access, query_id, Access, Pool};
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use model::models::{self, ActivityAction, ActivityEntity, AppUser, InviteMember, NewAppUser, ProjectMember, ProjectMemberInfo, ProjectRole};
+use model::schema::{app_user, project_member};
+use std::collections::HashMap;
+
+pub async fn get_members(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let pid: i32 = query_id(&query, "projectId")?;
+    check_project_access(conn, user.id, pid, Access::Read)?;
+    let members: Vec<(i32, String, ProjectRole)> = project_member::table
+        .inner_join(app_user::table)
+        .filter(project_member::project_id.eq(pid))
+        .select((project_member::user_id, app_user::email, project_member::role))
+        .order(app_user::email.asc())
+        .load(conn)?;
+    Ok(HttpResponse::Ok().json(
+        members
+            .into_iter()
+            .map(|(user_id, email, role)| ProjectMemberInfo { user_id, email, role })
+            .collect::<Vec<ProjectMemberInfo>>(),
+    ))
+}
+
+/// Shares a project by email. Unknown emails get a placeholder account that is
+/// claimed on their first sign-in or registration.
+pub async fn invite_member(
+    invite: web::Json<InviteMember>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    if invite.role == ProjectRole::Owner {
+        return Err(ApiError::Validation("a project has a single owner".to_owned()));
+    }
+    let invited_email = AppUser::normalize_email(&invite.email);
+    if !invited_email.contains('@') {
+        return Err(ApiError::Validation("a valid email is required".to_owned()));
+    }
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    check_project_access(conn, user.id, invite.project_id, Access::Manage)?;
+    let info = conn.transaction::<ProjectMemberInfo, ApiError, _>(|conn| {
+        let invited = match AppUser::by_email(conn, &invited_email) {
+            Some(u) => u,
+            None => diesel::insert_into(app_user::table)
+                .values(&NewAppUser {
+                    email: invited_email.clone(),
+                    placeholder: true,
+                    email_verified: false,
+                })
+                .get_result(conn)?,
+        };
+        if ProjectMember::role_of(conn, invite.project_id, invited.id).is_some() {
+            return Err(ApiError::Conflict(format!(
+                "{} is already a member of the project",
+                invited.email
+            )));
+        }
+        diesel::insert_into(project_member::table)
+            .values(&ProjectMember {
+                project_id: invite.project_id,
+                user_id: invited.id,
+                role: invite.role,
+                position: ProjectMember::next_position(conn, invited.id)?,
+            })
+            .execute(conn)?;
+        let info = ProjectMemberInfo {
+            user_id: invited.id,
+            email: invited.email,
+            role: invite.role,
+        };
+        let entity = (ActivityEntity::Member, info.user_id);
+        record_entity(conn, user.id, invite.project_id, entity, ActivityAction::Create, &info)?;
+        Ok(info)
+    })?;
+    Ok(HttpResponse::Ok().json(info))
+}
+
+/// Changes the role of a member, the owner keeps their role.
+pub async fn update_member(
+    member: web::Json<ProjectMember>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let p = check_project_access(conn, user.id, member.project_id, Access::Manage)?;
+    if member.user_id == p.owner_id || member.role == ProjectRole::Owner {
+        return Err(ApiError::Validation("the project owner can not be changed".to_owned()));
+    }
+    let current = ProjectMember::role_of(conn, member.project_id, member.user_id).ok_or(ApiError::NotFound)?;
+    let res = conn.transaction::<ProjectMember, ApiError, _>(|conn| {
+        let res: ProjectMember = diesel::update(
+            project_member::table
+                .filter(project_member::project_id.eq(member.project_id))
+                .filter(project_member::user_id.eq(member.user_id)),
+        )
+        .set(project_member::role.eq(member.role))
+        .get_result(conn)?;
+        let before = ProjectMember { role: current, ..res.clone() };
+        let entity = (ActivityEntity::Member, res.user_id);
+        record_changes(conn, user.id, res.project_id, entity, ActivityAction::Update, &before, &res)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// Removes a member, members may also remove themselves to leave a project.
+pub async fn remove_member(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let pid: i32 = query_id(&query, "projectId")?;
+    let uid: i32 = query_id(&query, "userId")?;
+    let access = if uid == user.id { Access::Read } else { Access::Manage };
+    let p: models::Project = check_project_access(conn, user.id, pid, access)?;
+    if uid == p.owner_id {
+        return Err(ApiError::Validation("the project owner can not be removed".to_owned()));
+    }
+    let res = conn.transaction::<ProjectMember, ApiError, _>(|conn| {
+        let res: ProjectMember = diesel::delete(
+            project_member::table
+                .filter(project_member::project_id.eq(pid))
+                .filter(project_member::user_id.eq(uid)),
+        )
+        .get_result(conn)?;
+        record_entity(conn, user.id, pid, (ActivityEntity::Member, uid), ActivityAction::Delete, &res)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res))
+}
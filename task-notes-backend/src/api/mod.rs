@@ -1,204 +1,752 @@
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use activity::{record, record_changes, record_entity};
+use events::Broadcaster;
 use model::models;
-use model::models::NewTask;
-use model::models::{NewProject, PatchProject};
+use model::models::{ActivityAction, ActivityEntity, ChangeEvent};
+use model::models::{MoveTask, NewTask, PatchTask, MAX_NOTES_LEN};
+use model::models::{NewProject, PatchProject, ProjectOrder, ProjectRole};
+use model::models::{NewTaskList, PatchTaskList, TaskListOrder};
 use model::schema::project;
 use model::schema::task;
 use actix_identity::Identity;
-use actix_identity::error::GetIdentityError;
-use actix_web::{web, HttpResponse};
+use actix_web::http::header;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use chrono::{Datelike, Duration, Utc};
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::*;
+use serde::Serialize;
+use query::{cursor_key, page, page_limit, start_of_day, utc_offset};
+use query::{Cursor, DueFilter, ProjectQuery, SortBy, TaskListQuery, TaskQuery};
 use std::collections::HashMap;
 
+#[macro_use]
+pub mod query;
+pub mod activity;
+pub mod checklist;
+pub mod events;
+pub mod label;
+pub mod member;
+pub mod search;
+pub mod trash;
+pub mod token;
+
 pub type Pool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+/// Parses a required integer query parameter such as `id` or `projectId`.
+pub fn query_id(query: &HashMap<String, String>, key: &str) -> Result<i32, ApiError> {
+    query
+        .get(key)
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| ApiError::Validation(format!("missing or invalid `{}` query parameter", key)))
+}
+
+/// The version a conditional update was made to: the `If-Match` tag, else the
+/// `version` of the body. `None` applies the update to the current version.
+fn expected_version(req: &HttpRequest, body: Option<i32>) -> Result<Option<i32>, ApiError> {
+    match req.get_header::<header::IfMatch>() {
+        Some(header::IfMatch::Items(tags)) if !tags.is_empty() => tags[0]
+            .tag()
+            .parse()
+            .map(Some)
+            .map_err(|_| ApiError::Validation(format!("`If-Match` {} is not a version", tags[0]))),
+        _ => Ok(body),
+    }
+}
+
+fn version_conflict(kind: &str, id: i32) -> ApiError {
+    ApiError::Conflict(format!(
+        "{} {} was changed in the meantime, load it again and reapply your changes",
+        kind, id
+    ))
+}
+
+/// The `ETag` of an entity at `version`.
+pub fn etag(version: i32) -> header::ETag {
+    header::ETag(header::EntityTag::new_strong(version.to_string()))
+}
+
+/// Answers with `body` and its `ETag`, or `304 Not Modified` if the client's
+/// `If-None-Match` already names `version`.
+fn versioned<T: Serialize>(req: &HttpRequest, version: i32, body: &T) -> HttpResponse {
+    let tag = etag(version);
+    if let Some(header::IfNoneMatch::Items(tags)) = req.get_header::<header::IfNoneMatch>() {
+        if tags.iter().any(|t| t.weak_eq(&tag.0)) {
+            return HttpResponse::NotModified().insert_header(tag).finish();
+        }
+    }
+    HttpResponse::Ok().insert_header(tag).json(body)
+}
+
+/// Json body limit of `/api/task`, escaping may double the size of the notes.
+pub const TASK_PAYLOAD_LIMIT: usize = 2 * MAX_NOTES_LEN + 1024;
+
+/// Rejects notes longer than `MAX_NOTES_LEN` bytes.
+fn check_notes_len(notes: &str) -> Result<(), ApiError> {
+    if notes.len() > MAX_NOTES_LEN {
+        return Err(ApiError::Validation(format!(
+            "notes are limited to {} bytes",
+            MAX_NOTES_LEN
+        )));
+    }
+    Ok(())
+}
+
 /// This handler uses json extractor with limit
 pub async fn create_task(
     task_item: web::Json<NewTask>,
     pool: web::Data<Pool>,
-    req_identity: Option<Identity>,
-) -> HttpResponse {
-    let conn: &mut PgConnection = &mut pool.get().unwrap();
-    if !check_project_owner(conn, &req_identity, task_item.project_id) {
-        return HttpResponse::BadRequest().finish();
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    check_notes_len(&task_item.notes)?;
+    check_project_access(conn, user.id, task_item.project_id, Access::Write)?;
+    if let Some(lid) = task_item.task_list_id {
+        check_task_list_in_project(conn, lid, task_item.project_id)?;
+    }
+    let mut new_task = task_item.into_inner();
+    if new_task.position.is_none() {
+        let siblings = task::table.filter(task::project_id.eq(new_task.project_id));
+        let last: Option<i32> = match new_task.task_list_id {
+            Some(lid) => siblings
+                .filter(task::task_list_id.eq(lid))
+                .select(diesel::dsl::max(task::position))
+                .first(conn)?,
+            None => siblings
+                .filter(task::task_list_id.is_null())
+                .select(diesel::dsl::max(task::position))
+                .first(conn)?,
+        };
+        new_task.position = Some(last.map_or(0, |p| p + 1));
     }
-    let res: models::Task = diesel::insert_into(task::table)
-        .values(&task_item.into_inner())
-        .get_result(conn)
-        .unwrap();
-    HttpResponse::Ok().json(res) // <- send json response
+    let res = conn.transaction::<models::Task, ApiError, _>(|conn| {
+        let res: models::Task = diesel::insert_into(task::table)
+            .values(&new_task)
+            .get_result(conn)?;
+        record_entity(conn, user.id, res.project_id, (ActivityEntity::Task, res.id), ActivityAction::Create, &res)?;
+        Ok(res)
+    })?;
+    broadcaster.publish(conn, res.project_id, &ChangeEvent::TaskChanged(res.clone()));
+    Ok(HttpResponse::Ok().json(res)) // <- send json response
 }
 
 pub async fn create_project(
     mut project_item: web::Json<NewProject>,
     pool: web::Data<Pool>,
-    req_identity: Option<Identity>,
-) -> HttpResponse {
-    println!("{:?}", project_item);
-    let conn: &mut PgConnection = &mut pool.get().unwrap();
-    project_item.owner_id = match user_id_from_identity(conn, &req_identity) {
-        Ok(oid) => oid,
-        _ => {
-            return HttpResponse::BadRequest().finish();
-        }
-    };
-    let res: models::Project = diesel::insert_into(project::table)
-        .values(&project_item.into_inner())
-        .get_result(conn)
-        .unwrap();
-    HttpResponse::Ok().json(res) // <- send json response
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    project_item.owner_id = user.id;
+    if project_item.priority.is_none() {
+        project_item.priority = Some(models::ProjectMember::next_position(conn, user.id)?);
+    }
+    let res = conn.transaction::<models::Project, ApiError, _>(|conn| {
+        let res: models::Project = diesel::insert_into(project::table)
+            .values(&project_item.into_inner())
+            .get_result(conn)?;
+        diesel::insert_into(model::schema::project_member::table)
+            .values(&models::ProjectMember {
+                project_id: res.id,
+                user_id: user.id,
+                role: ProjectRole::Owner,
+                position: res.priority,
+            })
+            .execute(conn)?;
+        record_entity(conn, user.id, res.id, (ActivityEntity::Project, res.id), ActivityAction::Create, &res)?;
+        Ok(res)
+    })?;
+    broadcaster.publish(conn, res.id, &ChangeEvent::ProjectChanged(res.clone()));
+    Ok(HttpResponse::Ok().json(res)) // <- send json response
 }
 
 pub async fn get_tasks(
-    query: web::Query<HashMap<String, String>>,
+    query: web::Query<TaskQuery>,
     pool: web::Data<Pool>,
-    req_identity: Option<Identity>,
-) -> HttpResponse {
-    use model::schema::app_user::dsl::*;
-    use model::schema::project::dsl::*;
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     use model::schema::task::dsl::*;
-    let conn: &mut PgConnection = &mut pool.get().unwrap();
-    let oid: i32 = match user_id_from_identity(conn, &req_identity) {
-        Ok(oid) => oid,
-        _ => {
-            return HttpResponse::BadRequest().finish();
+    use model::schema::{project_member, task_label};
+    let limit = page_limit(query.limit).map_err(ApiError::Validation)?;
+    let offset = utc_offset(query.utc_offset)
+        .ok_or_else(|| ApiError::Validation(format!("`utcOffset` {} is out of range", query.utc_offset)))?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+
+    let mut tasks = task.filter(deleted_at.is_null()).into_boxed();
+    match query.project_id {
+        Some(pid) => {
+            check_project_access(conn, user.id, pid, Access::Read)?;
+            tasks = tasks.filter(project_id.eq(pid));
+        }
+        None if query.due.is_some() => {
+            // due date views span all live projects of the caller
+            let member_of = project_member::table
+                .inner_join(project::table)
+                .filter(project_member::user_id.eq(user.id))
+                .filter(project::deleted_at.is_null())
+                .select(project_member::project_id);
+            tasks = tasks.filter(project_id.eq_any(member_of));
         }
+        None => return Err(ApiError::Validation("missing or invalid `projectId` query parameter".to_owned())),
+    }
+    if let Some(lid) = query.label {
+        let labelled = task_label::table
+            .filter(task_label::label_id.eq(lid))
+            .select(task_label::task_id);
+        tasks = tasks.filter(id.eq_any(labelled));
+    }
+    if let Some(done) = query.completed {
+        tasks = tasks.filter(completed.eq(done));
+    }
+    if let Some(filter) = query.due {
+        let now = Utc::now();
+        let today = start_of_day(now, offset);
+        tasks = match filter {
+            DueFilter::Overdue => tasks.filter(due_at.lt(now)).filter(completed.eq(false)),
+            DueFilter::Today => tasks.filter(due_at.ge(today)).filter(due_at.lt(today + Duration::days(1))),
+            DueFilter::ThisWeek => {
+                let weekday = now.with_timezone(&offset).weekday().num_days_from_monday();
+                let monday = today - Duration::days(weekday as i64);
+                tasks.filter(due_at.ge(monday)).filter(due_at.lt(monday + Duration::days(7)))
+            }
+        };
+    }
+    // due date views only hold tasks with a due date, manually sorted they come soonest first
+    let by_due = query.sort == SortBy::Manual && query.due.is_some();
+    let after_position = || cursor_key(query.cursor, Cursor::position);
+    let after_time = || cursor_key(query.cursor, Cursor::time);
+    let tasks = match query.sort {
+        SortBy::Manual if by_due => keyset!(tasks, due_at, id, false, after_time()?),
+        SortBy::Manual => keyset!(tasks, position, id, false, after_position()?),
+        SortBy::CreatedAt { desc } => keyset!(tasks, created_at, id, desc, after_time()?),
+        SortBy::UpdatedAt { desc } => keyset!(tasks, updated_at, id, desc, after_time()?),
     };
-    let pid: i32 = query.get("projectId").unwrap().parse().unwrap();
-
-    let tasks: Vec<(models::Task, models::Project)> = task
-        .inner_join(model::schema::project::table)
-        .filter(owner_id.eq(oid).and(project_id.eq(pid)))
-        .load(conn)
-        .unwrap();
-    //let mut tasks = task.filter(project_id.eq(pid)).load::<models::Task>(conn).unwrap();
-    HttpResponse::Ok().json(
-        tasks
-            .iter()
-            .map(|item| item.0.clone())
-            .collect::<Vec<models::Task>>(),
-    ) // <- send json response
+    let tasks = tasks.limit(limit + 1).load::<models::Task>(conn)?;
+    let sort = query.sort;
+    Ok(HttpResponse::Ok().json(page(tasks, limit, |t| match sort {
+        SortBy::Manual if by_due => Cursor::at_time(t.due_at.unwrap_or_default(), t.id),
+        SortBy::Manual => Cursor::at_position(t.position, t.id),
+        SortBy::CreatedAt { .. } => Cursor::at_time(t.created_at, t.id),
+        SortBy::UpdatedAt { .. } => Cursor::at_time(t.updated_at, t.id),
+    })))
 }
 
+type MemberProjectColumns = (
+    project::id,
+    project::owner_id,
+    project::title,
+    model::schema::project_member::position,
+    project::created_at,
+    project::updated_at,
+    project::deleted_at,
+    project::version,
+);
+
+/// The project columns with the member's own position in place of the priority.
+const MEMBER_PROJECT_COLUMNS: MemberProjectColumns = (
+    project::id,
+    project::owner_id,
+    project::title,
+    model::schema::project_member::position,
+    project::created_at,
+    project::updated_at,
+    project::deleted_at,
+    project::version,
+);
+
+/// Own projects and the ones shared with the user, one page at a time.
 pub async fn get_projects(
-    _query: web::Query<HashMap<String, String>>,
+    query: web::Query<ProjectQuery>,
     pool: web::Data<Pool>,
-    req_identity: Option<Identity>,
-) -> HttpResponse {
-    use model::schema::project::dsl::*;
-    let conn: &mut PgConnection = &mut pool.get().unwrap();
-
-    let oid: i32 = match user_id_from_identity(conn, &req_identity) {
-        Ok(oid) => oid,
-        _ => {
-            return HttpResponse::BadRequest().finish();
-        }
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::project_member;
+    let limit = page_limit(query.limit).map_err(ApiError::Validation)?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let projects = project::table
+        .inner_join(project_member::table)
+        .filter(project_member::user_id.eq(user.id))
+        .filter(project::deleted_at.is_null())
+        .select(MEMBER_PROJECT_COLUMNS)
+        .into_boxed();
+    let after_position = || cursor_key(query.cursor, Cursor::position);
+    let after_time = || cursor_key(query.cursor, Cursor::time);
+    let projects = match query.sort {
+        SortBy::Manual => keyset!(projects, project_member::position, project::id, false, after_position()?),
+        SortBy::CreatedAt { desc } => keyset!(projects, project::created_at, project::id, desc, after_time()?),
+        SortBy::UpdatedAt { desc } => keyset!(projects, project::updated_at, project::id, desc, after_time()?),
     };
-    let projects = project
-        .filter(owner_id.eq(oid))
-        .load::<models::Project>(conn)
-        .unwrap();
-    println!("{:?}", projects);
-    HttpResponse::Ok().json(projects)
+    let projects = projects.limit(limit + 1).load::<models::Project>(conn)?;
+    let sort = query.sort;
+    Ok(HttpResponse::Ok().json(page(projects, limit, |p| match sort {
+        SortBy::Manual => Cursor::at_position(p.priority, p.id),
+        SortBy::CreatedAt { .. } => Cursor::at_time(p.created_at, p.id),
+        SortBy::UpdatedAt { .. } => Cursor::at_time(p.updated_at, p.id),
+    })))
 }
 
-pub fn user_id_from_identity(
-    conn: &mut PgConnection,
-    req_identity: &Option<Identity>,
-) -> Result<i32, GetIdentityError> {
-    #[cfg(debug_assertions)]
-    return Ok(1);
-    #[cfg(not(debug_assertions))]
-    if let Some(i) = req_identity {
-        let user_email = i.id()?;
-        let user = models::AppUser::by_email(conn, &user_email).unwrap();
-        Ok(user.id)
-    }
+/// All own and shared projects in the user's order.
+fn load_projects(conn: &mut PgConnection, uid: i32) -> Result<Vec<models::Project>, ApiError> {
+    use model::schema::project_member;
+    Ok(project::table
+        .inner_join(project_member::table)
+        .filter(project_member::user_id.eq(uid))
+        .filter(project::deleted_at.is_null())
+        .select(MEMBER_PROJECT_COLUMNS)
+        .order((project_member::position.asc(), project::id.asc()))
+        .load::<models::Project>(conn)?)
 }
 
+/// Moves a task to the trash, see `trash::restore`.
 pub async fn delete_task(
     query: web::Query<HashMap<String, String>>,
     pool: web::Data<Pool>,
-    req_identity: Option<Identity>,
-) -> HttpResponse {
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     use model::schema::task::dsl::*;
-    let conn: &mut PgConnection = &mut pool.get().unwrap();
-    let tid: i32 = query.get("id").unwrap().parse().unwrap();
-    if !check_task_owner(conn, &req_identity, tid) {
-        return HttpResponse::BadRequest().finish();
-    }
-    let res: models::Task = diesel::delete(task.filter(id.eq(tid)))
-        .get_result(conn)
-        .unwrap();
-    HttpResponse::Ok().json(res) // <- send json response
-}
-
-pub fn check_task_owner(conn: &mut PgConnection, req_identity: &Option<Identity>, tid: i32) -> bool {
-    let _oid = match user_id_from_identity(conn, req_identity) {
-        Ok(id) => id,
-        _ => {
-            return false;
-        }
-    };
-    match models::Task::by_id(conn, tid) {
-        Some(t) => {
-            check_project_owner(conn, req_identity, t.project_id)
-        }
-        _ => {
-            false
-        }
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let tid: i32 = query_id(&query, "id")?;
+    check_task_access(conn, user.id, tid, Access::Write)?;
+    let res = conn.transaction::<models::Task, ApiError, _>(|conn| {
+        let res: models::Task = diesel::update(task.filter(id.eq(tid)))
+            .set(deleted_at.eq(Utc::now()))
+            .get_result(conn)?;
+        record_entity(conn, user.id, res.project_id, (ActivityEntity::Task, res.id), ActivityAction::Delete, &res)?;
+        Ok(res)
+    })?;
+    broadcaster.publish(conn, res.project_id, &ChangeEvent::TaskDeleted(res.clone()));
+    Ok(HttpResponse::Ok().json(res)) // <- send json response
+}
+
+/// Applies the changes if the task is still at the version the client expects,
+/// answers with the task and its new `ETag`.
+pub async fn update_task(
+    t: web::Json<PatchTask>,
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    if let Some(ref notes) = t.notes {
+        check_notes_len(notes)?;
+    }
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let current = check_task_access(conn, user.id, t.id, Access::Write)?;
+    if expected_version(&req, t.version)?.is_some_and(|v| v != current.version) {
+        return Err(version_conflict("task", t.id));
     }
+    if let Some(Some(lid)) = t.task_list_id {
+        check_task_list_in_project(conn, lid, current.project_id)?;
+    }
+    if t.is_empty() {
+        return Ok(HttpResponse::Ok().insert_header(etag(current.version)).json(current));
+    }
+    let res = conn.transaction::<models::Task, ApiError, _>(|conn| {
+        // the version filter catches edits that landed since `current` was read
+        let res: models::Task = update(task::table.find(t.id).filter(task::version.eq(current.version)))
+            .set((t.clone(), task::version.eq(current.version + 1)))
+            .get_result(conn)
+            .optional()?
+            .ok_or_else(|| version_conflict("task", t.id))?;
+        let entity = (ActivityEntity::Task, res.id);
+        record_changes(conn, user.id, res.project_id, entity, ActivityAction::Update, &current, &res)?;
+        Ok(res)
+    })?;
+    broadcaster.publish(conn, res.project_id, &ChangeEvent::TaskChanged(res.clone()));
+    Ok(HttpResponse::Ok().insert_header(etag(res.version)).json(res)) // <- send json response
+}
+
+/// `GET /api/task/{id}` with the task's version as `ETag`.
+pub async fn get_task(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let t = check_task_access(conn, user.id, path.into_inner(), Access::Read)?;
+    Ok(versioned(&req, t.version, &t))
 }
 
-pub fn check_project_owner(conn: &mut PgConnection, req_identity: &Option<Identity>, pid: i32) -> bool {
-    let oid = match user_id_from_identity(conn, req_identity) {
-        Ok(id) => id,
-        _ => {
-            return false;
+/// Moves a task next to another one or to the end of a task list and
+/// renumbers the target list in one transaction, returns the project's tasks.
+pub async fn move_task(
+    m: web::Json<MoveTask>,
+    pool: web::Data<Pool>,
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::task::dsl::*;
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let moved = check_task_access(conn, user.id, m.id, Access::Write)?;
+    let tasks = conn.transaction::<Vec<models::Task>, ApiError, _>(|conn| {
+        let anchor = match m.before.or(m.after) {
+            Some(aid) => match models::Task::by_id(conn, aid) {
+                Some(a) if a.id == moved.id => {
+                    return Err(ApiError::Validation("a task can not be moved next to itself".to_owned()))
+                }
+                Some(a) if a.project_id == moved.project_id && a.deleted_at.is_none() => Some(a),
+                _ => {
+                    return Err(ApiError::Validation(format!(
+                        "task {} does not belong to project {}",
+                        aid, moved.project_id
+                    )))
+                }
+            },
+            None => None,
+        };
+        let target_list = match anchor {
+            Some(ref a) => a.task_list_id,
+            None => m.task_list_id,
+        };
+        if let (None, Some(lid)) = (&anchor, target_list) {
+            check_task_list_in_project(conn, lid, moved.project_id)?;
         }
-    };
-    match models::Project::by_id(conn, pid) {
-        Some(p) => {
-            if p.owner_id != oid {
-                return false;
+        let mut ids: Vec<i32> = task
+            .filter(project_id.eq(moved.project_id))
+            .filter(deleted_at.is_null())
+            .order((position.asc(), id.asc()))
+            .load::<models::Task>(conn)?
+            .into_iter()
+            .filter(|t| t.task_list_id == target_list && t.id != moved.id)
+            .map(|t| t.id)
+            .collect();
+        let at = match anchor {
+            Some(a) => {
+                let i = ids.iter().position(|t| *t == a.id).unwrap_or(ids.len());
+                if m.before.is_some() { i } else { i + 1 }
             }
+            None => ids.len(),
+        };
+        ids.insert(at.min(ids.len()), moved.id);
+        // bumping the version of every task that moved makes edits based on
+        // the old position conflict
+        for (i, tid) in ids.iter().enumerate() {
+            let changed = position.ne(i as i32).or(task_list_id.is_distinct_from(target_list));
+            diesel::update(task.filter(id.eq(tid)).filter(changed))
+                .set((task_list_id.eq(target_list), position.eq(i as i32), version.eq(version + 1)))
+                .execute(conn)?;
         }
-        _ => {
-            return false;
+        let tasks: Vec<models::Task> = task
+            .filter(project_id.eq(moved.project_id))
+            .filter(deleted_at.is_null())
+            .order((position.asc(), id.asc()))
+            .load(conn)?;
+        if let Some(after) = tasks.iter().find(|t| t.id == moved.id) {
+            let entity = (ActivityEntity::Task, moved.id);
+            record_changes(conn, user.id, moved.project_id, entity, ActivityAction::Move, &moved, after)?;
         }
+        Ok(tasks)
+    })?;
+    broadcaster.publish(conn, moved.project_id, &ChangeEvent::TasksMoved(tasks.clone()));
+    Ok(HttpResponse::Ok().json(tasks))
+}
+
+/// What the caller is about to do with a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Manage,
+}
+
+/// Returns the task if the caller's role in its project allows `access`,
+/// trashed tasks are not found.
+pub fn check_task_access(
+    conn: &mut PgConnection,
+    oid: i32,
+    tid: i32,
+    access: Access,
+) -> Result<models::Task, ApiError> {
+    let t = models::Task::by_id(conn, tid)
+        .filter(|t| t.deleted_at.is_none())
+        .ok_or(ApiError::NotFound)?;
+    check_project_access(conn, oid, t.project_id, access)?;
+    Ok(t)
+}
+
+/// Returns the project if the caller's membership role allows `access`,
+/// trashed projects are not found.
+pub fn check_project_access(
+    conn: &mut PgConnection,
+    oid: i32,
+    pid: i32,
+    access: Access,
+) -> Result<models::Project, ApiError> {
+    let p = models::Project::by_id(conn, pid)
+        .filter(|p| p.deleted_at.is_none())
+        .ok_or(ApiError::NotFound)?;
+    let role = models::ProjectMember::role_of(conn, pid, oid).ok_or(ApiError::Forbidden)?;
+    let allowed = match access {
+        Access::Read => true,
+        Access::Write => role.can_write(),
+        Access::Manage => role.can_manage(),
     };
-    true
+    if !allowed {
+        return Err(ApiError::Forbidden);
+    }
+    Ok(p)
+}
+
+/// Returns the task list if the caller's role in its project allows `access`.
+pub fn check_task_list_access(
+    conn: &mut PgConnection,
+    oid: i32,
+    lid: i32,
+    access: Access,
+) -> Result<models::TaskList, ApiError> {
+    let l = models::TaskList::by_id(conn, lid).ok_or(ApiError::NotFound)?;
+    check_project_access(conn, oid, l.project_id, access)?;
+    Ok(l)
+}
+
+pub fn check_task_list_in_project(conn: &mut PgConnection, lid: i32, pid: i32) -> Result<(), ApiError> {
+    match models::TaskList::by_id(conn, lid) {
+        Some(l) if l.project_id == pid => Ok(()),
+        _ => Err(ApiError::Validation(format!(
+            "task list {} does not belong to project {}",
+            lid, pid
+        ))),
+    }
 }
 
+/// Moves a project with its task lists and tasks to the trash.
 pub async fn delete_project(
     query: web::Query<HashMap<String, String>>,
     pool: web::Data<Pool>,
-    req_identity: Option<Identity>,
-) -> HttpResponse {
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
     use model::schema::project::dsl::*;
-    let conn: &mut PgConnection = &mut pool.get().unwrap();
-    let pid: i32 = query.get("id").unwrap().parse().unwrap();
-    if !check_project_owner(conn, &req_identity, pid) {
-        return HttpResponse::BadRequest().finish();
-    }
-    let res: models::Project = diesel::delete(project.filter(id.eq(pid)))
-        .get_result(conn)
-        .unwrap();
-    HttpResponse::Ok().json(res) // <- send json response
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let pid: i32 = query_id(&query, "id")?;
+    check_project_access(conn, user.id, pid, Access::Manage)?;
+    let res = conn.transaction::<models::Project, ApiError, _>(|conn| {
+        let res: models::Project = diesel::update(project.filter(id.eq(pid)))
+            .set(deleted_at.eq(Utc::now()))
+            .get_result(conn)?;
+        record_entity(conn, user.id, pid, (ActivityEntity::Project, pid), ActivityAction::Delete, &res)?;
+        Ok(res)
+    })?;
+    broadcaster.publish(conn, pid, &ChangeEvent::ProjectDeleted(res.clone()));
+    Ok(HttpResponse::Ok().json(res)) // <- send json response
 }
 
+/// Applies the changes if the project is still at the version the client
+/// expects, answers with the project and its new `ETag`.
 pub async fn update_project(
     p: web::Json<PatchProject>,
+    req: HttpRequest,
     pool: web::Data<Pool>,
-    req_identity: Option<Identity>,
-) -> HttpResponse {
-    let conn: &mut PgConnection = &mut pool.get().unwrap();
-    if !check_project_owner(conn, &req_identity, p.id) {
-        return HttpResponse::BadRequest().finish();
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let current = check_project_access(conn, user.id, p.id, Access::Write)?;
+    if expected_version(&req, p.version)?.is_some_and(|v| v != current.version) {
+        return Err(version_conflict("project", p.id));
+    }
+    if p.is_empty() {
+        return Ok(HttpResponse::Ok().insert_header(etag(current.version)).json(current));
     }
-    let _res = update(&p.clone()).set(p.clone()).execute(conn).unwrap();
-    HttpResponse::Ok().json(p.into_inner()) // <- send json response
+    let res = conn.transaction::<models::Project, ApiError, _>(|conn| {
+        let res: models::Project = update(project::table.find(p.id).filter(project::version.eq(current.version)))
+            .set((p.clone(), project::version.eq(current.version + 1)))
+            .get_result(conn)
+            .optional()?
+            .ok_or_else(|| version_conflict("project", p.id))?;
+        let entity = (ActivityEntity::Project, res.id);
+        record_changes(conn, user.id, res.id, entity, ActivityAction::Update, &current, &res)?;
+        Ok(res)
+    })?;
+    broadcaster.publish(conn, res.id, &ChangeEvent::ProjectChanged(res.clone()));
+    Ok(HttpResponse::Ok().insert_header(etag(res.version)).json(res)) // <- send json response
+}
+
+/// `GET /api/project/{id}` with the project's version as `ETag`.
+pub async fn get_project(
+    path: web::Path<i32>,
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let p = check_project_access(conn, user.id, path.into_inner(), Access::Read)?;
+    Ok(versioned(&req, p.version, &p))
+}
+
+/// Rewrites the caller's own order of the listed projects, the other members keep theirs.
+pub async fn reorder_projects(
+    order: web::Json<ProjectOrder>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::project_member::dsl::*;
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let projects = conn.transaction::<Vec<models::Project>, ApiError, _>(|conn| {
+        for (i, pid) in order.project_ids.iter().enumerate() {
+            check_project_access(conn, user.id, *pid, Access::Read)?;
+            diesel::update(project_member.filter(project_id.eq(pid)).filter(user_id.eq(user.id)))
+                .set(position.eq(i as i32))
+                .execute(conn)?;
+        }
+        load_projects(conn, user.id)
+    })?;
+    Ok(HttpResponse::Ok().json(projects))
+}
+
+pub async fn create_task_list(
+    list_item: web::Json<NewTaskList>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::task_list::dsl::*;
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    check_project_access(conn, user.id, list_item.project_id, Access::Write)?;
+    let mut new_list = list_item.into_inner();
+    if new_list.position.is_none() {
+        let last: Option<i32> = task_list
+            .filter(project_id.eq(new_list.project_id))
+            .select(diesel::dsl::max(position))
+            .first(conn)?;
+        new_list.position = Some(last.map_or(0, |p| p + 1));
+    }
+    let res = conn.transaction::<models::TaskList, ApiError, _>(|conn| {
+        let res: models::TaskList = diesel::insert_into(task_list)
+            .values(&new_list)
+            .get_result(conn)?;
+        let entity = (ActivityEntity::TaskList, res.id);
+        record_entity(conn, user.id, res.project_id, entity, ActivityAction::Create, &res)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res)) // <- send json response
+}
+
+pub async fn get_task_lists(
+    query: web::Query<TaskListQuery>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::task_list::dsl::*;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    check_project_access(conn, user.id, query.project_id, Access::Read)?;
+    let lists = task_list.filter(project_id.eq(query.project_id)).into_boxed();
+    let lists = match query.sort {
+        SortBy::Manual => lists.order(position.asc()),
+        SortBy::CreatedAt { desc: false } => lists.order(created_at.asc()),
+        SortBy::CreatedAt { desc: true } => lists.order(created_at.desc()),
+        SortBy::UpdatedAt { desc: false } => lists.order(updated_at.asc()),
+        SortBy::UpdatedAt { desc: true } => lists.order(updated_at.desc()),
+    };
+    let lists = lists.then_order_by(id.asc()).load::<models::TaskList>(conn)?;
+    Ok(HttpResponse::Ok().json(lists))
+}
+
+pub async fn update_task_list(
+    l: web::Json<PatchTaskList>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let current = check_task_list_access(conn, user.id, l.id, Access::Write)?;
+    if l.is_empty() {
+        return Ok(HttpResponse::Ok().json(current));
+    }
+    let res = conn.transaction::<models::TaskList, ApiError, _>(|conn| {
+        let res: models::TaskList = update(&l.clone()).set(l.clone()).get_result(conn)?;
+        let entity = (ActivityEntity::TaskList, res.id);
+        record_changes(conn, user.id, res.project_id, entity, ActivityAction::Update, &current, &res)?;
+        Ok(res)
+    })?;
+    Ok(HttpResponse::Ok().json(res)) // <- send json response
+}
+
+/// Deletes the task list and moves its tasks to the trash, restoring them
+/// brings them back without a list.
+pub async fn delete_task_list(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::task_list::dsl::*;
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let lid: i32 = query_id(&query, "id")?;
+    check_task_list_access(conn, user.id, lid, Access::Write)?;
+    let (res, trashed) = conn.transaction::<(models::TaskList, Vec<models::Task>), ApiError, _>(|conn| {
+        let trashed: Vec<models::Task> =
+            diesel::update(task::table.filter(task::task_list_id.eq(lid)).filter(task::deleted_at.is_null()))
+                .set(task::deleted_at.eq(Utc::now()))
+                .get_results(conn)?;
+        for t in &trashed {
+            record_entity(conn, user.id, t.project_id, (ActivityEntity::Task, t.id), ActivityAction::Delete, t)?;
+        }
+        // the foreign key takes the list off its tasks, trashed ones included
+        let res: models::TaskList = diesel::delete(task_list.filter(id.eq(lid)))
+            .get_result(conn)?;
+        let entity = (ActivityEntity::TaskList, res.id);
+        record_entity(conn, user.id, res.project_id, entity, ActivityAction::Delete, &res)?;
+        Ok((res, trashed))
+    })?;
+    for t in trashed {
+        broadcaster.publish(conn, t.project_id, &ChangeEvent::TaskDeleted(t));
+    }
+    Ok(HttpResponse::Ok().json(res)) // <- send json response
+}
+
+/// Rewrites the positions of all listed task lists in one transaction.
+pub async fn reorder_task_lists(
+    order: web::Json<TaskListOrder>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::task_list::dsl::*;
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    check_project_access(conn, user.id, order.project_id, Access::Write)?;
+    let lists = conn.transaction::<Vec<models::TaskList>, ApiError, _>(|conn| {
+        for (i, lid) in order.task_list_ids.iter().enumerate() {
+            let updated = diesel::update(task_list.filter(id.eq(lid).and(project_id.eq(order.project_id))))
+                .set(position.eq(i as i32))
+                .execute(conn)?;
+            if updated == 0 {
+                return Err(ApiError::Validation(format!(
+                    "task list {} does not belong to project {}",
+                    lid, order.project_id
+                )));
+            }
+        }
+        let entity = (ActivityEntity::Project, order.project_id);
+        let diff = serde_json::json!({ "taskListIds": order.task_list_ids });
+        record(conn, user.id, order.project_id, entity, ActivityAction::Reorder, diff)?;
+        Ok(task_list
+            .filter(project_id.eq(order.project_id))
+            .order((position.asc(), id.asc()))
+            .load(conn)?)
+    })?;
+    Ok(HttpResponse::Ok().json(lists))
+}
+
+/// The signed in user, answers `401` otherwise so clients know to sign in.
+pub async fn get_user(pool: web::Data<Pool>, user: AuthUser) -> Result<HttpResponse, ApiError> {
+    use model::schema::app_user;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let app_user = app_user::table.find(user.id).first::<models::AppUser>(conn)?;
+    Ok(HttpResponse::Ok().json(app_user))
 }
 
 pub async fn logout(id: Identity) -> HttpResponse {
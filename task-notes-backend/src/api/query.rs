@@ -0,0 +1,120 @@
+//! Keyset paging of the list endpoints, their query strings live in `model::query`.
+use crate::error::ApiError;
+use chrono::{DateTime, Duration, FixedOffset, Timelike, Utc};
+use model::models::ListResponse;
+pub use model::query::{ActivityQuery, Cursor, DueFilter, ProjectQuery, SortBy, TaskListQuery, TaskQuery};
+pub use model::query::{DEFAULT_LIMIT, MAX_LIMIT};
+
+/// Orders a boxed query by `$key` and then id, and continues after the row
+/// `$after` (a key and id) points at.
+macro_rules! keyset {
+    ($query:expr, $key:expr, $id:expr, $desc:expr, $after:expr) => {{
+        let query = if $desc { $query.order($key.desc()) } else { $query.order($key.asc()) };
+        let query = query.then_order_by($id.asc());
+        match $after {
+            Some((k, i)) if $desc => query.filter($key.lt(k).or($key.eq(k).and($id.gt(i)))),
+            Some((k, i)) => query.filter($key.gt(k).or($key.eq(k).and($id.gt(i)))),
+            None => query,
+        }
+    }};
+}
+
+/// Reads the key of a cursor, cursors handed out for another sort are rejected.
+pub fn cursor_key<K>(cursor: Option<Cursor>, key: impl Fn(&Cursor) -> Option<K>) -> Result<Option<(K, i32)>, ApiError> {
+    match cursor {
+        Some(c) => key(&c)
+            .map(|k| Some((k, c.id)))
+            .ok_or_else(|| ApiError::Validation(format!("cursor `{}` does not fit the requested sort", c))),
+        None => Ok(None),
+    }
+}
+
+/// Cuts the `limit + 1` loaded rows down to a page and points `next_cursor`
+/// at its last row if there are more.
+pub fn page<T>(mut rows: Vec<T>, limit: i64, cursor: impl Fn(&T) -> Cursor) -> ListResponse<T> {
+    let more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let next_cursor = match rows.last() {
+        Some(last) if more => Some(cursor(last)),
+        _ => None,
+    };
+    ListResponse { items: rows, next_cursor }
+}
+
+/// The requested page size, `DEFAULT_LIMIT` if none was given.
+pub fn page_limit(limit: Option<i64>) -> Result<i64, String> {
+    match limit {
+        None => Ok(DEFAULT_LIMIT),
+        Some(l) if (1..=MAX_LIMIT).contains(&l) => Ok(l),
+        Some(l) => Err(format!("`limit` {} is out of range, expected 1 to {}", l, MAX_LIMIT)),
+    }
+}
+
+/// The offset of `minutes` east of UTC.
+pub fn utc_offset(minutes: i32) -> Option<FixedOffset> {
+    minutes.checked_mul(60).and_then(FixedOffset::east_opt)
+}
+
+/// Midnight of the day `now` falls on at `offset`.
+pub fn start_of_day(now: DateTime<Utc>, offset: FixedOffset) -> DateTime<Utc> {
+    let local = now.with_timezone(&offset);
+    now - Duration::seconds(local.num_seconds_from_midnight() as i64) - Duration::nanoseconds(local.nanosecond() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn cursor_keys_follow_the_sort() {
+        let time = Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 59).unwrap() + Duration::microseconds(250);
+        assert_eq!(Cursor::at_time(time, 1).time(), Some(time));
+        // timestamps of this century do not fit a position
+        let c = Cursor::at_time(Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap(), 1);
+        assert_eq!(c.position(), None);
+        assert!(cursor_key(Some(c), Cursor::position).is_err());
+        assert_eq!(cursor_key(Some(Cursor::at_position(3, 9)), Cursor::position).unwrap(), Some((3, 9)));
+        assert_eq!(cursor_key(None, Cursor::position).unwrap(), None);
+    }
+
+    /// Rows of `(key, id)`, sorted by key and then id like `keyset!` orders them.
+    fn rows(n: i32) -> Vec<(i64, i32)> {
+        (0..n).map(|i| (i as i64 / 2, i)).collect()
+    }
+
+    #[test]
+    fn page_points_at_its_last_row_when_there_are_more() {
+        let list = page(rows(3), 2, |&(key, id)| Cursor { key, id });
+        assert_eq!(list.items, vec![(0, 0), (0, 1)]);
+        // the next page starts after both rows sharing key 0
+        assert_eq!(list.next_cursor, Some(Cursor { key: 0, id: 1 }));
+    }
+
+    #[test]
+    fn last_page_has_no_cursor() {
+        let full = page(rows(2), 2, |&(key, id)| Cursor { key, id });
+        assert_eq!(full.items.len(), 2);
+        assert_eq!(full.next_cursor, None);
+        let empty = page(Vec::<(i64, i32)>::new(), 2, |&(key, id)| Cursor { key, id });
+        assert!(empty.items.is_empty());
+        assert_eq!(empty.next_cursor, None);
+    }
+
+    #[test]
+    fn limit_is_bounded() {
+        assert_eq!(page_limit(None), Ok(DEFAULT_LIMIT));
+        assert_eq!(page_limit(Some(MAX_LIMIT)), Ok(MAX_LIMIT));
+        assert!(page_limit(Some(0)).is_err());
+        assert!(page_limit(Some(MAX_LIMIT + 1)).is_err());
+    }
+
+    #[test]
+    fn days_start_at_local_midnight() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 1, 30, 0).unwrap();
+        assert_eq!(start_of_day(now, utc_offset(0).unwrap()), Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap());
+        // 1:30 UTC is still the evening before in New York
+        assert_eq!(start_of_day(now, utc_offset(-240).unwrap()), Utc.with_ymd_and_hms(2026, 10, 17, 4, 0, 0).unwrap());
+        assert_eq!(utc_offset(i32::MAX), None);
+    }
+}
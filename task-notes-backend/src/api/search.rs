@@ -0,0 +1,63 @@
+use crate::api::Pool;
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text};
+use model::models::SearchHit;
+use std::collections::HashMap;
+
+const DEFAULT_LIMIT: i32 = 20;
+const MAX_LIMIT: i32 = 100;
+
+/// Full text search over the titles of the caller's projects, task lists and tasks,
+/// trashed ones are left out.
+/// `q` takes web search syntax (`"exact phrase"`, `or`, `-excluded`).
+pub async fn search(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    let q = query
+        .get("q")
+        .map(|q| q.trim())
+        .ok_or_else(|| ApiError::Validation("missing `q` query parameter".to_owned()))?;
+    let limit = match query.get("limit") {
+        Some(l) => match l.parse::<i32>() {
+            Ok(l) if l > 0 => l.min(MAX_LIMIT),
+            _ => return Err(ApiError::Validation(format!("invalid `limit` `{}`", l))),
+        },
+        None => DEFAULT_LIMIT,
+    };
+    if q.is_empty() {
+        return Ok(HttpResponse::Ok().json(Vec::<SearchHit>::new()));
+    }
+    // the default `<b>` markers would turn titles into markup, the sentinels are
+    // dropped from the titles so they only ever mark matches
+    let headline_options = format!(
+        "HighlightAll=true, StartSel={}, StopSel={}",
+        SearchHit::MATCH_START,
+        SearchHit::MATCH_END
+    );
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let hits = diesel::sql_query(
+        "SELECT d.kind, d.entity_id AS id, d.project_id, d.title, \
+                ts_headline('english', translate(d.title, $4, ''), q, $5) AS snippet, \
+                ts_rank(d.document, q) AS rank \
+         FROM search_document d \
+         JOIN project_member m ON m.project_id = d.project_id AND m.user_id = $1 \
+         JOIN project p ON p.id = d.project_id AND p.deleted_at IS NULL \
+         LEFT JOIN task t ON d.kind = 'task' AND t.id = d.entity_id, \
+              websearch_to_tsquery('english', $2) q \
+         WHERE d.document @@ q AND t.deleted_at IS NULL \
+         ORDER BY rank DESC, d.kind, d.entity_id \
+         LIMIT $3",
+    )
+    .bind::<Integer, _>(user.id)
+    .bind::<Text, _>(q)
+    .bind::<Integer, _>(limit)
+    .bind::<Text, _>(format!("{}{}", SearchHit::MATCH_START, SearchHit::MATCH_END))
+    .bind::<Text, _>(headline_options)
+    .load::<SearchHit>(conn)?;
+    Ok(HttpResponse::Ok().json(hits))
+}
@@ -0,0 +1,69 @@
+use crate::api::{query_id, Pool};
+use crate::auth::{generate_token, hash_token, AuthUser};
+use crate::error::ApiError;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use model::models::{ApiToken, CreateApiToken, CreatedApiToken, NewApiToken};
+use model::schema::api_token;
+use std::collections::HashMap;
+
+/// Mints a personal api token, the secret is only returned by this call.
+pub async fn create_token(
+    token_item: web::Json<CreateApiToken>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_session()?;
+    let name = token_item.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::Validation("token name is required".to_owned()));
+    }
+    let expires_at = token_item
+        .expires_in_days
+        .map(|days| {
+            chrono::Utc::now()
+                .checked_add_signed(chrono::Duration::days(days as i64))
+                .ok_or_else(|| ApiError::Validation("expiresInDays is too far in the future".to_owned()))
+        })
+        .transpose()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let secret = generate_token();
+    let new_token = NewApiToken {
+        user_id: user.id,
+        name: name.to_owned(),
+        token_hash: hash_token(&secret),
+        scope: token_item.scope,
+        expires_at,
+    };
+    let token: ApiToken = diesel::insert_into(api_token::table)
+        .values(&new_token)
+        .get_result(conn)?;
+    Ok(HttpResponse::Ok().json(CreatedApiToken { token, secret }))
+}
+
+pub async fn get_tokens(
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::api_token::dsl::*;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let tokens = api_token
+        .filter(user_id.eq(user.id))
+        .order(created_at.desc())
+        .load::<ApiToken>(conn)?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+pub async fn delete_token(
+    query: web::Query<HashMap<String, String>>,
+    pool: web::Data<Pool>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    use model::schema::api_token::dsl::*;
+    user.require_session()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let tid: i32 = query_id(&query, "id")?;
+    let res: ApiToken = diesel::delete(api_token.filter(id.eq(tid).and(user_id.eq(user.id))))
+        .get_result(conn)?;
+    Ok(HttpResponse::Ok().json(res))
+}
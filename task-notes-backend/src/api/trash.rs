@@ -0,0 +1,121 @@
+use crate::api::activity::record_entity;
+use crate::api::events::Broadcaster;
+use crate::api::{check_project_access, Access, Pool};
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use model::models::{ActivityAction, ActivityEntity, ChangeEvent, Project, ProjectMember, Task, Trash, TrashItem};
+use model::schema::{project, project_member, task};
+
+/// Lists the trashed projects of the caller and the trashed tasks of their live projects.
+pub async fn get_trash(pool: web::Data<Pool>, user: AuthUser) -> Result<HttpResponse, ApiError> {
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let member_of = project_member::table
+        .filter(project_member::user_id.eq(user.id))
+        .select(project_member::project_id);
+    let projects = project::table
+        .filter(project::id.eq_any(member_of))
+        .filter(project::deleted_at.is_not_null())
+        .order((project::deleted_at.desc(), project::id.asc()))
+        .load::<Project>(conn)?;
+    let tasks = task::table
+        .inner_join(project::table)
+        .filter(project::id.eq_any(member_of))
+        .filter(project::deleted_at.is_null())
+        .filter(task::deleted_at.is_not_null())
+        .select(task::all_columns)
+        .order((task::deleted_at.desc(), task::id.asc()))
+        .load::<Task>(conn)?;
+    Ok(HttpResponse::Ok().json(Trash { projects, tasks }))
+}
+
+/// Takes a project or task out of the trash and answers with it. Restoring a
+/// project takes the owner, a task needs write access to its live project.
+pub async fn restore(
+    item: web::Json<TrashItem>,
+    pool: web::Data<Pool>,
+    broadcaster: web::Data<Broadcaster>,
+    user: AuthUser,
+) -> Result<HttpResponse, ApiError> {
+    user.require_write()?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let restored = match item.into_inner() {
+        TrashItem::Project(pid) => {
+            // non-members get the same 404 as for a project that does not exist
+            let role = ProjectMember::role_of(conn, pid, user.id).ok_or(ApiError::NotFound)?;
+            if !role.can_manage() {
+                return Err(ApiError::Forbidden);
+            }
+            let p: Project = project::table.find(pid).first(conn)?;
+            if p.deleted_at.is_none() {
+                return Err(ApiError::Validation(format!("project {} is not in the trash", pid)));
+            }
+            let p = conn.transaction::<Project, ApiError, _>(|conn| {
+                let p: Project = diesel::update(project::table.find(pid))
+                    .set(project::deleted_at.eq(None::<DateTime<Utc>>))
+                    .get_result(conn)?;
+                record_entity(conn, user.id, pid, (ActivityEntity::Project, pid), ActivityAction::Restore, &p)?;
+                Ok(p)
+            })?;
+            broadcaster.publish(conn, pid, &ChangeEvent::ProjectChanged(p.clone()));
+            let own: i32 = project_member::table
+                .find((pid, user.id))
+                .select(project_member::position)
+                .first(conn)?;
+            Trash { projects: vec![Project { priority: own, ..p }], tasks: Vec::new() }
+        }
+        TrashItem::Task(tid) => {
+            let t: Option<Task> = task::table.find(tid).first(conn).optional()?;
+            let t = t
+                .filter(|t| ProjectMember::role_of(conn, t.project_id, user.id).is_some())
+                .ok_or(ApiError::NotFound)?;
+            check_project_access(conn, user.id, t.project_id, Access::Write)?;
+            if t.deleted_at.is_none() {
+                return Err(ApiError::Validation(format!("task {} is not in the trash", tid)));
+            }
+            let t = conn.transaction::<Task, ApiError, _>(|conn| {
+                let t: Task = diesel::update(task::table.find(tid))
+                    .set(task::deleted_at.eq(None::<DateTime<Utc>>))
+                    .get_result(conn)?;
+                record_entity(conn, user.id, t.project_id, (ActivityEntity::Task, tid), ActivityAction::Restore, &t)?;
+                Ok(t)
+            })?;
+            broadcaster.publish(conn, t.project_id, &ChangeEvent::TaskChanged(t.clone()));
+            Trash { projects: Vec::new(), tasks: vec![t] }
+        }
+    };
+    Ok(HttpResponse::Ok().json(restored))
+}
+
+/// Deletes projects and tasks for good once they have been in the trash for
+/// longer than `retention`, returns how many projects and tasks went.
+pub fn purge(conn: &mut PgConnection, retention: Duration) -> QueryResult<(usize, usize)> {
+    let cutoff = Utc::now() - retention;
+    conn.transaction(|conn| {
+        let tasks = diesel::delete(task::table.filter(task::deleted_at.lt(cutoff))).execute(conn)?;
+        let projects = diesel::delete(project::table.filter(project::deleted_at.lt(cutoff))).execute(conn)?;
+        Ok((projects, tasks))
+    })
+}
+
+/// Purges the trash once an hour, runs for the lifetime of the server.
+pub async fn purge_job(pool: Pool, retention: Duration) {
+    let mut interval = actix_web::rt::time::interval(std::time::Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        let pool = pool.clone();
+        let purged = web::block(move || -> Result<(usize, usize), ApiError> {
+            let conn: &mut PgConnection = &mut *pool.get()?;
+            Ok(purge(conn, retention)?)
+        })
+        .await;
+        match purged {
+            Ok(Ok((0, 0))) => {}
+            Ok(Ok((projects, tasks))) => log::info!("Purged {} projects and {} tasks from the trash", projects, tasks),
+            Ok(Err(e)) => log::warn!("Purging the trash failed: {:?}", e),
+            Err(e) => log::warn!("Purging the trash failed: {}", e),
+        }
+    }
+}
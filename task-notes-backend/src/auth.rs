@@ -2,20 +2,30 @@ use crate::config;
 use oauth2::{basic::BasicClient, TokenResponse};
 // Alternatively, this can be oauth2::curl::http_client or a custom.
 use crate::diesel::prelude::*;
-use model::models::NewAppUser;
-use model::schema::app_user;
+use crate::error::ApiError;
+use model::models::{Credentials, NewAppUser, PasswordCredential};
+use model::schema::{api_token, app_user, password_credential};
 use crate::Pool;
 use actix_http::*;
-use actix_identity::Identity;
-use actix_web::{web, Error, HttpRequest, HttpResponse, Responder, ResponseError};
+use actix_identity::{Identity, IdentityExt};
+use actix_session::SessionExt;
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
 use diesel::query_dsl::RunQueryDsl;
 use diesel::PgConnection;
-use model::models::AppUser;
+use futures::future::{ready, LocalBoxFuture, Ready};
+use model::models::{ApiToken, AppUser, TokenScope};
+use rand::distributions::{Alphanumeric, DistString};
+use sha2::{Digest, Sha256};
 use oauth2::{
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge, RedirectUrl,
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl,
     RevocationUrl, Scope, TokenUrl,
 };
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GoogleProfile {
@@ -31,63 +41,465 @@ pub struct GoogleProfile {
     pub locale: Option<String>,
 }
 
+/// A way for the user to prove who they are.
+///
+/// Every provider resolves to a verified email address, the session is then
+/// established the same way by `sign_in`.
+pub trait IdentityProvider: Send + Sync {
+    /// Path the provider sends the browser back to, handled by `callback`.
+    fn callback_path(&self) -> &'static str;
+
+    /// Starts the sign-in flow, usually by redirecting the browser.
+    fn start(&self, req: &HttpRequest) -> Result<HttpResponse, ApiError>;
+
+    /// Completes the sign-in flow and returns the verified email address.
+    fn finish<'a>(
+        &'a self,
+        req: &'a HttpRequest,
+        query: &'a HashMap<String, String>,
+    ) -> LocalBoxFuture<'a, Result<String, ApiError>>;
+}
+
+/// Picks the identity provider enabled by the configuration.
+pub fn build_identity_provider(config: &config::Config) -> Result<Arc<dyn IdentityProvider>, String> {
+    if config.dev_login {
+        log::warn!("Development login is enabled, anyone can sign in as any user");
+        return Ok(Arc::new(DevLoginProvider));
+    }
+    Ok(Arc::new(GoogleProvider {
+        client: build_google_auth_client(config)?,
+    }))
+}
+
 #[inline]
-pub fn build_google_auth_client(config: &config::Config) -> BasicClient {
-    let google_client_id = ClientId::new(config.google_client_id.clone().unwrap());
-    let google_client_secret = ClientSecret::new(config.google_client_secret.clone().unwrap());
+pub fn build_google_auth_client(config: &config::Config) -> Result<BasicClient, String> {
+    let google_client_id = ClientId::new(
+        config.google_client_id.clone().ok_or("GOOGLE_CLIENT_ID must be set")?,
+    );
+    let google_client_secret = ClientSecret::new(
+        config.google_client_secret.clone().ok_or("GOOGLE_CLIENT_SECRET must be set")?,
+    );
+    let domain_root_url = config.domain_root_url.clone().ok_or("DOMAIN_ROOT_URL must be set")?;
     let auth_url = AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".to_string())
         .expect("Invalid authorization endpoint URL");
     let token_url = TokenUrl::new("https://www.googleapis.com/oauth2/v3/token".to_string())
         .expect("Invalid token endpoint URL");
 
     // Set up the config for the Google OAuth2 process.
-    BasicClient::new(
+    Ok(BasicClient::new(
         google_client_id,
         Some(google_client_secret),
         auth_url,
         Some(token_url),
     )
     .set_redirect_uri(
-        RedirectUrl::new(format!(
-            "{}google_oauth/",
-            &config.domain_root_url.clone().unwrap()
-        ))
-        .expect("Invalid redirect URL"),
+        RedirectUrl::new(format!("{}google_oauth/", &domain_root_url))
+            .map_err(|_| "Invalid DOMAIN_ROOT_URL")?,
     )
     .set_revocation_uri(
         RevocationUrl::new("https://oauth2.googleapis.com/revoke".to_string())
             .expect("Invalid revocation endpoint URL"),
-    )
+    ))
+}
+
+/// Session key of the csrf state of a running Google sign-in.
+const OAUTH_STATE_KEY: &str = "oauth_state";
+
+/// Sign in with a Google account.
+pub struct GoogleProvider {
+    client: BasicClient,
+}
+
+impl IdentityProvider for GoogleProvider {
+    fn callback_path(&self) -> &'static str {
+        "/google_oauth/"
+    }
+
+    fn start(&self, req: &HttpRequest) -> Result<HttpResponse, ApiError> {
+        let (authorize_url, csrf_state) = self
+            .client
+            .authorize_url(CsrfToken::new_random)
+            .add_scope(Scope::new(
+                "https://www.googleapis.com/auth/userinfo.profile".to_owned(),
+            ))
+            .add_scope(Scope::new(
+                "https://www.googleapis.com/auth/userinfo.email".to_owned(),
+            ))
+            .add_scope(Scope::new(
+                "https://www.googleapis.com/auth/plus.me".to_owned(),
+            ))
+            .add_extra_param("access_type", "offline")
+            .url();
+        // compared with the `state` Google sends back to the callback
+        req.get_session()
+            .insert(OAUTH_STATE_KEY, csrf_state.secret())
+            .map_err(|e| ApiError::Internal(format!("session error: {:?}", e)))?;
+        Ok(HttpResponse::Found()
+            .insert_header((header::LOCATION, authorize_url.as_str()))
+            .finish())
+    }
+
+    fn finish<'a>(
+        &'a self,
+        req: &'a HttpRequest,
+        query: &'a HashMap<String, String>,
+    ) -> LocalBoxFuture<'a, Result<String, ApiError>> {
+        use oauth2::reqwest::async_http_client;
+        Box::pin(async move {
+            let session = req.get_session();
+            let expected_state = session.get::<String>(OAUTH_STATE_KEY).ok().flatten();
+            session.remove(OAUTH_STATE_KEY);
+            match (expected_state, query.get("state")) {
+                (Some(expected), Some(state)) if &expected == state => {}
+                _ => {
+                    log::debug!("oauth state does not match the session");
+                    return Err(ApiError::Unauthenticated);
+                }
+            }
+            let code = query.get("code").ok_or(ApiError::Unauthenticated)?;
+            let token = self
+                .client
+                .exchange_code(AuthorizationCode::new(code.to_string()))
+                .request_async(async_http_client)
+                .await
+                .map_err(|e| {
+                    log::debug!("{:?}", e);
+                    ApiError::Unauthenticated
+                })?;
+            let web_client = req
+                .app_data::<web::Data<awc::Client>>()
+                .map(|c| c.get_ref().clone())
+                .unwrap_or_default();
+            let mut response = web_client
+                .get(&format!(
+                    "https://www.googleapis.com/userinfo/v2/me?access_token={}",
+                    token.access_token().secret()
+                ))
+                .send()
+                .await
+                .map_err(|e| {
+                    log::debug!("{:?}", e);
+                    ApiError::Unauthenticated
+                })?;
+            let data = response.body().await.map_err(|e| {
+                log::debug!("{:?}", e);
+                ApiError::Unauthenticated
+            })?;
+            let profile: GoogleProfile = serde_json::from_slice(&data).map_err(|e| {
+                log::debug!("bad gauth response: {:?}", e);
+                ApiError::Unauthenticated
+            })?;
+            // sign-in links the session to the account of this email
+            if profile.verified_email != Some(true) {
+                log::debug!("google account email is not verified");
+                return Err(ApiError::Unauthenticated);
+            }
+            profile.email.ok_or(ApiError::Unauthenticated)
+        })
+    }
+}
+
+/// Local development login: pick a known user or type any email address.
+///
+/// Enabled with `TM_DEV_LOGIN=true`, never turn it on in production.
+pub struct DevLoginProvider;
+
+impl IdentityProvider for DevLoginProvider {
+    fn callback_path(&self) -> &'static str {
+        "/dev_login/"
+    }
+
+    fn start(&self, req: &HttpRequest) -> Result<HttpResponse, ApiError> {
+        let pool = req
+            .app_data::<web::Data<Pool>>()
+            .expect("pool is registered as app data");
+        let templates = req
+            .app_data::<web::Data<tera::Tera>>()
+            .expect("templates are registered as app data");
+        let conn: &mut PgConnection = &mut *pool.get()?;
+        let emails: Vec<String> = app_user::dsl::app_user
+            .select(app_user::dsl::email)
+            .order(app_user::dsl::email.asc())
+            .load(conn)?;
+        let mut ctx = tera::Context::new();
+        ctx.insert("emails", &emails);
+        ctx.insert("action", self.callback_path());
+        let body = templates.render("dev_login.html", &ctx).map_err(|e| {
+            log::error!("{:?}", e);
+            ApiError::Internal("template error".to_owned())
+        })?;
+        Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(body))
+    }
+
+    fn finish<'a>(
+        &'a self,
+        _req: &'a HttpRequest,
+        query: &'a HashMap<String, String>,
+    ) -> LocalBoxFuture<'a, Result<String, ApiError>> {
+        Box::pin(async move {
+            match query.get("email").map(|e| e.trim()) {
+                Some(email) if email.contains('@') => Ok(email.to_owned()),
+                _ => Err(ApiError::Validation("a valid email is required".to_owned())),
+            }
+        })
+    }
+}
+
+/// Stores the email in the session identity, creating the user on first sign-in.
+pub fn sign_in(req: &HttpRequest, pool: &Pool, user_email: &str) -> Result<AppUser, ApiError> {
+    let user_email = AppUser::normalize_email(user_email);
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let user = match AppUser::by_email(conn, &user_email) {
+        Some(user) if user.placeholder || !user.email_verified => claim(conn, user.id)?,
+        Some(user) => user,
+        None => {
+            log::info!("Creating user");
+            let usr = NewAppUser {
+                email: user_email.clone(),
+                placeholder: false,
+                email_verified: true,
+            };
+            diesel::insert_into(model::schema::app_user::table)
+                .values(&usr)
+                .get_result(conn)?
+        }
+    };
+    remember(req, &user, false)?;
+    Ok(user)
+}
+
+/// Hands an account to the verified owner of its email: the placeholder of an
+/// invite becomes a regular account, and the password and api tokens of an
+/// account registered without proof of the email stop working.
+fn claim(conn: &mut PgConnection, user_id: i32) -> Result<AppUser, ApiError> {
+    log::info!("Claiming user {} with a verified email", user_id);
+    conn.transaction::<AppUser, ApiError, _>(|conn| {
+        diesel::delete(password_credential::table.filter(password_credential::user_id.eq(user_id)))
+            .execute(conn)?;
+        diesel::delete(api_token::table.filter(api_token::user_id.eq(user_id))).execute(conn)?;
+        Ok(diesel::update(app_user::table.find(user_id))
+            .set((app_user::placeholder.eq(false), app_user::email_verified.eq(true)))
+            .get_result(conn)?)
+    })
+}
+
+/// Session key marking sessions signed in with a password, they end once the
+/// account's password credential is gone.
+const PASSWORD_SESSION_KEY: &str = "password_session";
+
+fn remember(req: &HttpRequest, user: &AppUser, with_password: bool) -> Result<(), ApiError> {
+    log::info!("Setting identity: {}", &user.email);
+    Identity::login(&req.extensions(), user.email.clone()).map_err(|e| {
+        log::error!("{:?}", e);
+        ApiError::Unauthenticated
+    })?;
+    req.get_session()
+        .insert(PASSWORD_SESSION_KEY, with_password)
+        .map_err(|e| ApiError::Internal(format!("session error: {:?}", e)))?;
+    Ok(())
+}
+
+const MIN_PASSWORD_LEN: usize = 8;
+
+fn hash_password(password: &str) -> Result<String, ApiError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| ApiError::Internal(format!("password hashing failed: {}", e)))
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Creates an account with an email and password and signs it in.
+pub async fn register(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    credentials: web::Json<Credentials>,
+) -> Result<HttpResponse, ApiError> {
+    let user_email = AppUser::normalize_email(&credentials.email);
+    if !user_email.contains('@') {
+        return Err(ApiError::Validation("a valid email is required".to_owned()));
+    }
+    if credentials.password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(ApiError::Validation(format!(
+            "password must be at least {} characters long",
+            MIN_PASSWORD_LEN
+        )));
+    }
+    let password_hash = hash_password(&credentials.password)?;
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    let user = conn.transaction::<AppUser, ApiError, _>(|conn| {
+        let user: AppUser = match AppUser::by_email(conn, &user_email) {
+            // a password proves nothing about the mailbox, the invite's access
+            // goes to whoever signs in with a verified email
+            Some(user) if user.placeholder => {
+                return Err(ApiError::Conflict(
+                    "this email has a pending invite, sign in with it to accept".to_owned(),
+                ))
+            }
+            Some(_) => return Err(ApiError::Conflict("email is already registered".to_owned())),
+            None => diesel::insert_into(app_user::table)
+                .values(&NewAppUser {
+                    email: user_email.clone(),
+                    placeholder: false,
+                    email_verified: false,
+                })
+                .get_result(conn)?,
+        };
+        diesel::insert_into(password_credential::table)
+            .values(&PasswordCredential {
+                user_id: user.id,
+                password_hash,
+            })
+            .execute(conn)?;
+        Ok(user)
+    })?;
+    remember(&req, &user, true)?;
+    Ok(HttpResponse::Ok().json(user))
+}
+
+/// Signs in with an email and password registered through `register`.
+pub async fn password_login(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    credentials: web::Json<Credentials>,
+) -> Result<HttpResponse, ApiError> {
+    let user_email = AppUser::normalize_email(&credentials.email);
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    // same error for unknown emails and wrong passwords
+    let user = AppUser::by_email(conn, &user_email).ok_or(ApiError::Unauthenticated)?;
+    let credential = PasswordCredential::by_user_id(conn, user.id).ok_or(ApiError::Unauthenticated)?;
+    if !verify_password(&credentials.password, &credential.password_hash) {
+        return Err(ApiError::Unauthenticated);
+    }
+    remember(&req, &user, true)?;
+    Ok(HttpResponse::Ok().json(user))
 }
 
 pub async fn login(
-    _template: web::Data<tera::Tera>,
-    oauth_client: web::Data<BasicClient>,
-) -> Result<HttpResponse, Error> {
-    let _ctx = tera::Context::new();
-    let client = oauth_client;
-    // Google supports Proof Key for Code Exchange (PKCE - https://oauth.net/2/pkce/).
-    // Create a PKCE code verifier and SHA-256 encode it as a code challenge.
-    let (pkce_code_challenge, pkce_code_verifier) = PkceCodeChallenge::new_random_sha256();
-    let (authorize_url, _csrf_state) = client
-        .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new(
-            "https://www.googleapis.com/auth/userinfo.profile".to_owned(),
-        ))
-        .add_scope(Scope::new(
-            "https://www.googleapis.com/auth/userinfo.email".to_owned(),
-        ))
-        .add_scope(Scope::new(
-            "https://www.googleapis.com/auth/plus.me".to_owned(),
-        ))
-        .add_extra_param("access_type", "offline")
-        //.set_pkce_challenge(pkce_code_challenge)
-        .url();
+    req: HttpRequest,
+    provider: web::Data<dyn IdentityProvider>,
+) -> Result<HttpResponse, ApiError> {
+    provider.start(&req)
+}
+
+pub async fn callback(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    query: web::Query<HashMap<String, String>>,
+    provider: web::Data<dyn IdentityProvider>,
+) -> Result<HttpResponse, ApiError> {
+    let user_email = provider.finish(&req, &query).await?;
+    sign_in(&req, &pool, &user_email)?;
     Ok(HttpResponse::Found()
-        .header(header::LOCATION, authorize_url.as_str())
+        .insert_header((header::LOCATION, "/"))
         .finish())
 }
 
+/// The caller of an api handler.
+///
+/// Resolved from the session identity or from an `Authorization: Bearer`
+/// personal api token, session users always get read-write access.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub id: i32,
+    pub scope: TokenScope,
+    /// The api token the caller sent, `None` for session users.
+    pub token_id: Option<i32>,
+}
+
+impl AuthUser {
+    /// Rejects callers authenticated with an api token, so a leaked token can
+    /// not mint a longer lived one.
+    pub fn require_session(&self) -> Result<(), ApiError> {
+        match self.token_id {
+            None => Ok(()),
+            Some(_) => Err(ApiError::Forbidden),
+        }
+    }
+
+    /// Rejects callers authenticated with a read-only token.
+    pub fn require_write(&self) -> Result<(), ApiError> {
+        match self.scope {
+            TokenScope::ReadWrite => Ok(()),
+            TokenScope::ReadOnly => Err(ApiError::Forbidden),
+        }
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(authenticate(req))
+    }
+}
+
+fn authenticate(req: &HttpRequest) -> Result<AuthUser, ApiError> {
+    let pool = req
+        .app_data::<web::Data<Pool>>()
+        .expect("pool is registered as app data");
+    let conn: &mut PgConnection = &mut *pool.get()?;
+    if let Some(value) = req.headers().get(header::AUTHORIZATION) {
+        let secret = value
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or(ApiError::Unauthenticated)?;
+        return user_from_token(conn, secret.trim());
+    }
+    let user_email = req.get_identity()?.id()?;
+    let user = AppUser::by_email(conn, &user_email).ok_or(ApiError::Unauthenticated)?;
+    let with_password = req.get_session().get::<bool>(PASSWORD_SESSION_KEY).ok().flatten();
+    if with_password == Some(true) && PasswordCredential::by_user_id(conn, user.id).is_none() {
+        // the verified owner of the email took the account over
+        return Err(ApiError::Unauthenticated);
+    }
+    Ok(AuthUser {
+        id: user.id,
+        scope: TokenScope::ReadWrite,
+        token_id: None,
+    })
+}
+
+fn user_from_token(conn: &mut PgConnection, secret: &str) -> Result<AuthUser, ApiError> {
+    use model::schema::api_token::dsl::*;
+    let token = ApiToken::by_hash(conn, &hash_token(secret)).ok_or(ApiError::Unauthenticated)?;
+    let now = chrono::Utc::now();
+    if token.is_expired(now) {
+        return Err(ApiError::Unauthenticated);
+    }
+    diesel::update(api_token.filter(id.eq(token.id)))
+        .set(last_used_at.eq(now))
+        .execute(conn)?;
+    Ok(AuthUser {
+        id: token.user_id,
+        scope: token.scope,
+        token_id: Some(token.id),
+    })
+}
+
+const TOKEN_PREFIX: &str = "tn_";
+
+/// Generates the secret of a new personal api token.
+pub fn generate_token() -> String {
+    format!("{}{}", TOKEN_PREFIX, Alphanumeric.sample_string(&mut rand::thread_rng(), 40))
+}
+
+/// Tokens are long random strings, a plain sha256 is enough to store them.
+pub fn hash_token(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
 #[derive(Debug, Default)]
 pub struct WebClientError {}
 
@@ -115,66 +527,3 @@ impl ResponseError for WebClientError {
         res.set_body(body::BoxBody::new(buf))
     }
 }
-pub async fn google_oauth(
-    req: HttpRequest,
-    pool: web::Data<Pool>,
-    query: web::Query<HashMap<String, String>>,
-    oauth_client: web::Data<BasicClient>,
-    web_client: web::Data<awc::Client>,
-) -> impl Responder {
-    use oauth2::reqwest::{async_http_client, http_client};
-    let code = AuthorizationCode::new(query.get("code").unwrap().to_string());
-    let token = oauth_client
-        .exchange_code(code)
-        .request_async(async_http_client)
-        .await;
-
-    if token.is_err() {
-        log::debug!("{:?}", token);
-        return actix_web::web::Redirect::to("/").using_status_code(StatusCode::BAD_REQUEST);
-    }
-    let response = web_client
-        .get(&format!(
-            "https://www.googleapis.com/userinfo/v2/me?access_token={}",
-            token.unwrap().access_token().secret()
-        ))
-        .send()
-        .await
-        .map_err(|e| {
-            log::debug!("{:?}", e);
-            Error::from(WebClientError {})
-        })
-        .unwrap()
-        .body();
-    let data = response.await.unwrap();
-    let json: GoogleProfile = serde_json::from_slice(&data).expect("bad gauth response");
-    let user_email = json.email.clone().expect("no email");
-    log::info!("Setting identity: {}", &user_email);
-    Identity::login(&req.extensions(), user_email.clone()).unwrap();
-
-    let conn: &mut PgConnection = &mut pool.get().unwrap();
-    let user = app_user::dsl::app_user
-        .filter(app_user::dsl::email.eq(user_email.clone()))
-        .load::<model::models::AppUser>(conn);
-    match user {
-        Err(_) => {}
-        Ok(vec) => {
-            if vec.is_empty() {
-                log::info!("Creating user");
-                let usr = NewAppUser {
-                    email: user_email.clone(),
-                };
-                let _res: AppUser = diesel::insert_into(model::schema::app_user::table)
-                    .values(&usr)
-                    .get_result(conn)
-                    .unwrap();
-            }
-        }
-    };
-
-    actix_web::web::Redirect::to("/").using_status_code(StatusCode::FOUND)
-
-    /*let json: GoogleProfile = serde_json::from_reader(resp.unwrap()).expect("bad gauth response");
-    req.remember(json.email.expect("no email"));
-    fut_ok(HttpResponse::Found().header("location", "/api/").finish()).responder()*/
-}
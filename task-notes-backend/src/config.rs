@@ -3,29 +3,36 @@ pub struct Config {
     pub google_client_id: Option<String>,
     pub google_client_secret: Option<String>,
     pub domain_root_url: Option<String>,
+    /// Replaces Google sign-in with the local development login.
+    pub dev_login: bool,
+    /// Days trashed projects and tasks are kept before they are purged.
+    pub trash_retention_days: i64,
+    /// Hex encoded key (at least 64 bytes) signing the session cookies.
+    pub session_key: Option<String>,
 }
 
 impl Config {
-    #[cfg(debug_assertions)]
-    pub fn read() -> Config {
-        Config {
-            google_client_id: None,
-            google_client_secret: None,
-            domain_root_url: None,
-        }
-    }
-
-    #[cfg(not(debug_assertions))]
     pub fn read() -> Config {
         Config::read_from_env()
     }
 
-    #[allow(dead_code)]
     pub fn read_from_env() -> Config {
         Config {
             google_client_id: std::env::var("GOOGLE_CLIENT_ID").ok(),
             google_client_secret: std::env::var("GOOGLE_CLIENT_SECRET").ok(),
             domain_root_url: std::env::var("DOMAIN_ROOT_URL").ok(),
+            dev_login: env_flag("TM_DEV_LOGIN"),
+            trash_retention_days: std::env::var("TM_TRASH_RETENTION_DAYS")
+                .ok()
+                .and_then(|d| d.parse().ok())
+                .unwrap_or(30),
+            session_key: std::env::var("TM_SESSION_KEY").ok(),
         }
     }
 }
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|v| matches!(v.as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
@@ -0,0 +1,153 @@
+use actix_identity::error::GetIdentityError;
+use actix_web::error::{JsonPayloadError, QueryPayloadError};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use diesel::result::DatabaseErrorKind;
+use model::models::ErrorResponse;
+
+/// Errors returned by the api handlers, rendered as a json `ErrorResponse`.
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound,
+    Forbidden,
+    Unauthenticated,
+    Validation(String),
+    Conflict(String),
+    PayloadTooLarge(String),
+    /// The database can not serve the request right now, the connection broke
+    /// or a transaction lost a race with another one. Retrying may succeed.
+    Database(diesel::result::Error),
+    Pool(r2d2::Error),
+    Internal(String),
+}
+
+impl ApiError {
+    /// Machine readable error code sent to the client.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound => "not_found",
+            ApiError::Forbidden => "forbidden",
+            ApiError::Unauthenticated => "unauthenticated",
+            ApiError::Validation(_) => "validation",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::Database(_) => "database_unavailable",
+            ApiError::Pool(_) => "database_unavailable",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::NotFound => f.write_str("Resource not found"),
+            ApiError::Forbidden => f.write_str("Access to the resource is not allowed"),
+            ApiError::Unauthenticated => f.write_str("Authentication required"),
+            ApiError::Validation(msg) => f.write_str(msg),
+            ApiError::Conflict(msg) => f.write_str(msg),
+            ApiError::PayloadTooLarge(msg) => f.write_str(msg),
+            ApiError::Database(_) | ApiError::Pool(_) => f.write_str("Database is unavailable"),
+            ApiError::Internal(_) => f.write_str("Internal server error"),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::Unauthenticated => StatusCode::UNAUTHORIZED,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::Database(_) | ApiError::Pool(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let ApiError::Database(ref e) = self {
+            log::error!("{:?}", e);
+        }
+        if let ApiError::Pool(ref e) = self {
+            log::error!("{:?}", e);
+        }
+        if let ApiError::Internal(ref e) = self {
+            log::error!("{}", e);
+        }
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            code: self.code().to_owned(),
+            message: self.to_string(),
+        })
+    }
+}
+
+impl From<diesel::result::Error> for ApiError {
+    fn from(e: diesel::result::Error) -> Self {
+        use diesel::result::Error;
+        match e {
+            Error::NotFound => ApiError::NotFound,
+            // the constraint text names tables and columns, it stays in the log
+            Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                log::debug!("{}", info.message());
+                ApiError::Conflict("an entry with the same value already exists".to_owned())
+            }
+            Error::DatabaseError(DatabaseErrorKind::RestrictViolation, info) => {
+                log::debug!("{}", info.message());
+                ApiError::Conflict("the entry is still in use".to_owned())
+            }
+            Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+                log::debug!("{}", info.message());
+                ApiError::Validation("the request refers to an entry that does not exist".to_owned())
+            }
+            Error::DatabaseError(DatabaseErrorKind::CheckViolation, info) => {
+                log::debug!("{}", info.message());
+                ApiError::Validation("a value of the request is out of range".to_owned())
+            }
+            Error::DatabaseError(DatabaseErrorKind::NotNullViolation, info) => {
+                log::debug!("{}", info.message());
+                ApiError::Validation("a required value of the request is missing".to_owned())
+            }
+            e @ Error::DatabaseError(
+                DatabaseErrorKind::ClosedConnection
+                | DatabaseErrorKind::UnableToSendCommand
+                | DatabaseErrorKind::SerializationFailure
+                | DatabaseErrorKind::ReadOnlyTransaction,
+                _,
+            ) => ApiError::Database(e),
+            e @ (Error::BrokenTransactionManager | Error::RollbackErrorOnCommit { .. }) => ApiError::Database(e),
+            // anything else is a query the server should not have sent
+            e => ApiError::Internal(format!("{:?}", e)),
+        }
+    }
+}
+
+impl From<r2d2::Error> for ApiError {
+    fn from(e: r2d2::Error) -> Self {
+        ApiError::Pool(e)
+    }
+}
+
+impl From<GetIdentityError> for ApiError {
+    fn from(_: GetIdentityError) -> Self {
+        ApiError::Unauthenticated
+    }
+}
+
+/// Reports malformed json bodies as validation errors and bodies above the
+/// resource's `JsonConfig` limit as too large.
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match err {
+        JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+            ApiError::PayloadTooLarge(err.to_string()).into()
+        }
+        err => ApiError::Validation(err.to_string()).into(),
+    }
+}
+
+/// Reports malformed query strings as validation errors.
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::Validation(err.to_string()).into()
+}
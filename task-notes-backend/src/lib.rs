@@ -21,6 +21,10 @@ pub fn create_task<'a>(conn: &mut PgConnection, title: &'a str) -> Task {
         title: title.to_owned(),
         project_id: 0,
         task_list_id: None,
+        position: None,
+        due_at: None,
+        remind_at: None,
+        notes: String::new(),
     };
 
     diesel::insert_into(task::table)
@@ -8,11 +8,13 @@ extern crate dotenv;
 pub mod api;
 pub mod auth;
 pub mod config;
+pub mod error;
 // pub mod auth_middleware;
 
 use actix_session::config::PersistentSession;
 use actix_web::cookie::time::Duration;
 use actix_web::{cookie::Key, middleware, web, App, HttpServer};
+use std::convert::TryFrom;
 
 use actix_cors::Cors;
 use api::*;
@@ -97,45 +99,120 @@ async fn main() -> std::io::Result<()> {
         .build(manager)
         .expect("Failed to create pool.");
 
+    let config = config::Config::read();
+    actix_web::rt::spawn(trash::purge_job(
+        pool.clone(),
+        chrono::Duration::days(config.trash_retention_days),
+    ));
+
+    let broadcaster = events::Broadcaster::create();
+
+    let identity_provider = auth::build_identity_provider(&config)
+        .map_err(std::io::Error::other)?;
+    let callback_path = identity_provider.callback_path();
+    // every worker has to sign the session cookies with the same key
+    let secret_key = session_key(&config)?;
+
     let app = move || {
         //Initialize AppState
-        let secret_key = Key::generate();
         let web_client = Client::default();
         let tera = Tera::new("templates/**/*").unwrap();
         let cors = Cors::default()
             .allow_any_origin()
             .allowed_methods(vec!["GET", "POST", "DELETE", "PATCH"])
             .allow_any_header()
+            .expose_headers(vec![actix_web::http::header::ETAG])
             .supports_credentials();
 
         const ONE_MINUTE: Duration = Duration::minutes(60);
 
-        //Initialize OAuth
-        let oauth_client = auth::build_google_auth_client(&config::Config::read_from_env());
         App::new()
             .app_data(web::Data::new(tera))
-            .app_data(web::Data::new(oauth_client))
+            .app_data(web::Data::from(identity_provider.clone()))
             .app_data(web::Data::new(web_client))
             .app_data(web::Data::new(pool.clone()))
-            .app_data(web::JsonConfig::default().limit(4096)) // <- limit size of the payload (global configuration)
+            .app_data(web::Data::from(broadcaster.clone()))
+            .app_data(web::JsonConfig::default().limit(4096).error_handler(error::json_error_handler)) // <- limit size of the payload (global configuration)
+            .app_data(web::QueryConfig::default().error_handler(error::query_error_handler))
             .service(
                 web::resource("/api/task")
-                    .app_data(web::JsonConfig::default().limit(1024)) // <- limit size of the payload (resource level)
+                    .app_data(web::JsonConfig::default().limit(TASK_PAYLOAD_LIMIT).error_handler(error::json_error_handler)) // <- limit size of the payload (resource level)
                     .route(web::post().to(create_task))
                     .route(web::get().to(get_tasks))
-                    .route(web::delete().to(delete_task)),
+                    .route(web::delete().to(delete_task))
+                    .route(web::patch().to(update_task)),
             )
+            .service(web::resource("/api/task/move").route(web::post().to(move_task)))
+            .service(web::resource(r"/api/task/{id:\d+}").route(web::get().to(get_task)))
             .service(
                 web::resource("/api/project")
-                    .app_data(web::JsonConfig::default().limit(1024)) // <- limit size of the payload (resource level)
+                    .app_data(web::JsonConfig::default().limit(1024).error_handler(error::json_error_handler)) // <- limit size of the payload (resource level)
                     .route(web::post().to(create_project))
                     .route(web::get().to(get_projects))
                     .route(web::delete().to(delete_project))
                     .route(web::patch().to(update_project)),
             )
+            .service(
+                web::resource("/api/checklist")
+                    .app_data(web::JsonConfig::default().limit(1024).error_handler(error::json_error_handler)) // <- limit size of the payload (resource level)
+                    .route(web::post().to(checklist::create_checklist_item))
+                    .route(web::get().to(checklist::get_checklist_items))
+                    .route(web::delete().to(checklist::delete_checklist_item))
+                    .route(web::patch().to(checklist::update_checklist_item)),
+            )
+            .service(web::resource("/api/checklist/reorder").route(web::post().to(checklist::reorder_checklist)))
+            .service(
+                web::resource("/api/label")
+                    .app_data(web::JsonConfig::default().limit(1024).error_handler(error::json_error_handler)) // <- limit size of the payload (resource level)
+                    .route(web::post().to(label::create_label))
+                    .route(web::get().to(label::get_labels))
+                    .route(web::delete().to(label::delete_label))
+                    .route(web::patch().to(label::update_label)),
+            )
+            .service(
+                web::resource("/api/task/label")
+                    .app_data(web::JsonConfig::default().limit(1024).error_handler(error::json_error_handler)) // <- limit size of the payload (resource level)
+                    .route(web::post().to(label::attach_label))
+                    .route(web::get().to(label::get_task_labels))
+                    .route(web::delete().to(label::detach_label)),
+            )
+            .service(web::resource("/api/project/reorder").route(web::post().to(reorder_projects)))
+            .service(web::resource(r"/api/project/{id:\d+}").route(web::get().to(get_project)))
+            .service(
+                web::resource("/api/project/member")
+                    .app_data(web::JsonConfig::default().limit(1024).error_handler(error::json_error_handler)) // <- limit size of the payload (resource level)
+                    .route(web::post().to(member::invite_member))
+                    .route(web::get().to(member::get_members))
+                    .route(web::delete().to(member::remove_member))
+                    .route(web::patch().to(member::update_member)),
+            )
+            .service(web::resource("/api/events").route(web::get().to(events::events)))
+            .service(web::resource("/api/project/{id}/activity").route(web::get().to(activity::get_activity)))
+            .service(
+                web::resource("/api/task_list")
+                    .app_data(web::JsonConfig::default().limit(1024).error_handler(error::json_error_handler)) // <- limit size of the payload (resource level)
+                    .route(web::post().to(create_task_list))
+                    .route(web::get().to(get_task_lists))
+                    .route(web::delete().to(delete_task_list))
+                    .route(web::patch().to(update_task_list)),
+            )
+            .service(web::resource("/api/task_list/reorder").route(web::post().to(reorder_task_lists)))
+            .service(web::resource("/api/search").route(web::get().to(search::search)))
+            .service(web::resource("/api/trash").route(web::get().to(trash::get_trash)))
+            .service(web::resource("/api/trash/restore").route(web::post().to(trash::restore)))
             .service(web::resource("/login").route(web::get().to(auth::login)))
+            .service(
+                web::resource("/api/token")
+                    .app_data(web::JsonConfig::default().limit(1024).error_handler(error::json_error_handler)) // <- limit size of the payload (resource level)
+                    .route(web::post().to(token::create_token))
+                    .route(web::get().to(token::get_tokens))
+                    .route(web::delete().to(token::delete_token)),
+            )
+            .service(web::resource("/api/register").route(web::post().to(auth::register)))
+            .service(web::resource("/api/login").route(web::post().to(auth::password_login)))
+            .service(web::resource("/api/user").route(web::get().to(get_user)))
             .service(web::resource("/api/logout").to(logout))
-            .service(web::resource("/google_oauth/").route(web::get().to(auth::google_oauth)))
+            .service(web::resource(callback_path).route(web::get().to(auth::callback)))
             .service(actix_files::Files::new("/assets", "./assets/").use_last_modified(false))
             .service(
                 actix_files::Files::new("/", "../task-notes-gui/dist")
@@ -164,6 +241,25 @@ async fn main() -> std::io::Result<()> {
         .await
 }
 
+/// The key of `TM_SESSION_KEY`, or a random one that only lasts until the restart.
+fn session_key(config: &config::Config) -> std::io::Result<Key> {
+    match &config.session_key {
+        Some(hex_key) => hex::decode(hex_key.trim())
+            .ok()
+            .and_then(|bytes| Key::try_from(&bytes[..]).ok())
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "TM_SESSION_KEY must be at least 64 hex encoded bytes",
+                )
+            }),
+        None => {
+            log::warn!("TM_SESSION_KEY is not set, sessions end when the server restarts");
+            Ok(Key::generate())
+        }
+    }
+}
+
 fn get_bind_host() -> String {
     std::env::var("TM_BIND_HOST")
         .ok()
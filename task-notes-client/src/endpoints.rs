@@ -0,0 +1,211 @@
+//! One method per endpoint of the backend, grouped like its routes.
+use crate::query::Query;
+use crate::{ApiError, Client};
+use model::models::{Activity, ApiToken, AppUser, CreateApiToken, CreatedApiToken, Credentials, ListResponse};
+use model::models::{Checklist, ChecklistItem, ChecklistOrder, NewChecklistItem, PatchChecklistItem};
+use model::models::{InviteMember, ProjectMember, ProjectMemberInfo};
+use model::models::{Label, NewLabel, PatchLabel, TaskLabel};
+use model::models::{MoveTask, NewTask, PatchTask, Task};
+use model::models::{NewProject, PatchProject, Project, ProjectOrder};
+use model::models::{NewTaskList, PatchTaskList, TaskList, TaskListOrder};
+use model::models::{SearchHit, Trash, TrashItem};
+use model::query::{ActivityQuery, ProjectQuery, TaskQuery};
+
+/// Query of the endpoints that take a `projectId`.
+fn of_project(project_id: i32) -> Query {
+    Query::new().with("projectId", project_id)
+}
+
+/// Query of the delete endpoints, they name the row by `id`.
+fn by_id(id: i32) -> Query {
+    Query::new().with("id", id)
+}
+
+impl Client {
+    pub async fn get_user(&self) -> Result<AppUser, ApiError> {
+        self.get("api/user", &Query::new()).await
+    }
+
+    /// Creates an account and signs it in, the session is kept as a cookie.
+    pub async fn register(&self, credentials: &Credentials) -> Result<AppUser, ApiError> {
+        self.post("api/register", credentials).await
+    }
+
+    /// Signs in with an email and password, the session is kept as a cookie.
+    pub async fn login(&self, credentials: &Credentials) -> Result<AppUser, ApiError> {
+        self.post("api/login", credentials).await
+    }
+
+    pub async fn get_projects(&self, query: &ProjectQuery) -> Result<ListResponse<Project>, ApiError> {
+        self.get("api/project", &Query::encode(query)).await
+    }
+
+    pub async fn get_project(&self, project_id: i32) -> Result<Project, ApiError> {
+        self.get(&format!("api/project/{}", project_id), &Query::new()).await
+    }
+
+    pub async fn create_project(&self, project: &NewProject) -> Result<Project, ApiError> {
+        self.post("api/project", project).await
+    }
+
+    /// Fails with the code `conflict` if `changes.version` is not the current one.
+    pub async fn edit_project(&self, changes: &PatchProject) -> Result<Project, ApiError> {
+        self.patch("api/project", changes).await
+    }
+
+    /// Moves the project to the trash.
+    pub async fn delete_project(&self, project_id: i32) -> Result<Project, ApiError> {
+        self.delete("api/project", &by_id(project_id)).await
+    }
+
+    pub async fn reorder_projects(&self, order: &ProjectOrder) -> Result<Vec<Project>, ApiError> {
+        self.post("api/project/reorder", order).await
+    }
+
+    pub async fn get_members(&self, project_id: i32) -> Result<Vec<ProjectMemberInfo>, ApiError> {
+        self.get("api/project/member", &of_project(project_id)).await
+    }
+
+    pub async fn invite_member(&self, invite: &InviteMember) -> Result<ProjectMemberInfo, ApiError> {
+        self.post("api/project/member", invite).await
+    }
+
+    /// Changes the role of a member.
+    pub async fn edit_member(&self, member: &ProjectMember) -> Result<ProjectMember, ApiError> {
+        self.patch("api/project/member", member).await
+    }
+
+    pub async fn remove_member(&self, project_id: i32, user_id: i32) -> Result<ProjectMember, ApiError> {
+        self.delete("api/project/member", &of_project(project_id).with("userId", user_id)).await
+    }
+
+    /// The project's history, newest first.
+    pub async fn get_activity(&self, project_id: i32, query: &ActivityQuery) -> Result<ListResponse<Activity>, ApiError> {
+        self.get(&format!("api/project/{}/activity", project_id), &Query::encode(query)).await
+    }
+
+    pub async fn get_tasks(&self, query: &TaskQuery) -> Result<ListResponse<Task>, ApiError> {
+        self.get("api/task", &Query::encode(query)).await
+    }
+
+    pub async fn get_task(&self, task_id: i32) -> Result<Task, ApiError> {
+        self.get(&format!("api/task/{}", task_id), &Query::new()).await
+    }
+
+    pub async fn create_task(&self, task: &NewTask) -> Result<Task, ApiError> {
+        self.post("api/task", task).await
+    }
+
+    /// Fails with the code `conflict` if `changes.version` is not the current one.
+    pub async fn edit_task(&self, changes: &PatchTask) -> Result<Task, ApiError> {
+        self.patch("api/task", changes).await
+    }
+
+    /// Moves the task to the trash.
+    pub async fn delete_task(&self, task_id: i32) -> Result<Task, ApiError> {
+        self.delete("api/task", &by_id(task_id)).await
+    }
+
+    /// Moves a task within or between sections, returns the tasks of the project.
+    pub async fn move_task(&self, m: &MoveTask) -> Result<Vec<Task>, ApiError> {
+        self.post("api/task/move", m).await
+    }
+
+    pub async fn get_task_lists(&self, project_id: i32) -> Result<Vec<TaskList>, ApiError> {
+        self.get("api/task_list", &of_project(project_id)).await
+    }
+
+    pub async fn create_task_list(&self, task_list: &NewTaskList) -> Result<TaskList, ApiError> {
+        self.post("api/task_list", task_list).await
+    }
+
+    pub async fn edit_task_list(&self, changes: &PatchTaskList) -> Result<TaskList, ApiError> {
+        self.patch("api/task_list", changes).await
+    }
+
+    pub async fn delete_task_list(&self, task_list_id: i32) -> Result<TaskList, ApiError> {
+        self.delete("api/task_list", &by_id(task_list_id)).await
+    }
+
+    pub async fn reorder_task_lists(&self, order: &TaskListOrder) -> Result<Vec<TaskList>, ApiError> {
+        self.post("api/task_list/reorder", order).await
+    }
+
+    /// Checklist items of every task of the project.
+    pub async fn get_checklist_items(&self, project_id: i32) -> Result<Vec<ChecklistItem>, ApiError> {
+        self.get("api/checklist", &of_project(project_id)).await
+    }
+
+    pub async fn create_checklist_item(&self, item: &NewChecklistItem) -> Result<Checklist, ApiError> {
+        self.post("api/checklist", item).await
+    }
+
+    pub async fn edit_checklist_item(&self, changes: &PatchChecklistItem) -> Result<Checklist, ApiError> {
+        self.patch("api/checklist", changes).await
+    }
+
+    pub async fn delete_checklist_item(&self, item_id: i32) -> Result<Checklist, ApiError> {
+        self.delete("api/checklist", &by_id(item_id)).await
+    }
+
+    pub async fn reorder_checklist(&self, order: &ChecklistOrder) -> Result<Checklist, ApiError> {
+        self.post("api/checklist/reorder", order).await
+    }
+
+    pub async fn get_labels(&self, project_id: i32) -> Result<Vec<Label>, ApiError> {
+        self.get("api/label", &of_project(project_id)).await
+    }
+
+    pub async fn create_label(&self, label: &NewLabel) -> Result<Label, ApiError> {
+        self.post("api/label", label).await
+    }
+
+    pub async fn edit_label(&self, changes: &PatchLabel) -> Result<Label, ApiError> {
+        self.patch("api/label", changes).await
+    }
+
+    pub async fn delete_label(&self, label_id: i32) -> Result<Label, ApiError> {
+        self.delete("api/label", &by_id(label_id)).await
+    }
+
+    /// Which labels the tasks of the project carry.
+    pub async fn get_task_labels(&self, project_id: i32) -> Result<Vec<TaskLabel>, ApiError> {
+        self.get("api/task/label", &of_project(project_id)).await
+    }
+
+    pub async fn attach_label(&self, attach: &TaskLabel) -> Result<TaskLabel, ApiError> {
+        self.post("api/task/label", attach).await
+    }
+
+    pub async fn detach_label(&self, detach: &TaskLabel) -> Result<TaskLabel, ApiError> {
+        let query = Query::new().with("taskId", detach.task_id).with("labelId", detach.label_id);
+        self.delete("api/task/label", &query).await
+    }
+
+    /// Projects and tasks matching `q`, the server picks the number of hits if `limit` is `None`.
+    pub async fn search(&self, q: &str, limit: Option<i32>) -> Result<Vec<SearchHit>, ApiError> {
+        self.get("api/search", &Query::new().with("q", q).with_opt("limit", limit)).await
+    }
+
+    pub async fn get_trash(&self) -> Result<Trash, ApiError> {
+        self.get("api/trash", &Query::new()).await
+    }
+
+    /// Takes a project or task out of the trash, returns what came back.
+    pub async fn restore(&self, item: &TrashItem) -> Result<Trash, ApiError> {
+        self.post("api/trash/restore", item).await
+    }
+
+    /// The secret of the token is only ever returned here.
+    pub async fn create_token(&self, token: &CreateApiToken) -> Result<CreatedApiToken, ApiError> {
+        self.post("api/token", token).await
+    }
+
+    pub async fn get_tokens(&self) -> Result<Vec<ApiToken>, ApiError> {
+        self.get("api/token", &Query::new()).await
+    }
+
+    pub async fn delete_token(&self, token_id: i32) -> Result<ApiToken, ApiError> {
+        self.delete("api/token", &by_id(token_id)).await
+    }
+}
@@ -0,0 +1,40 @@
+use model::models::ErrorResponse;
+use std::fmt;
+
+/// Why a request failed.
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    /// The server could not be reached.
+    Network(String),
+    /// The server answered with an error status, and the error it described if it did.
+    Status(u16, Option<ErrorResponse>),
+    /// The response did not have the shape the request expects.
+    Decode(String),
+}
+
+impl ApiError {
+    /// The machine readable code the server sent along, see `ErrorResponse`.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ApiError::Status(_, Some(e)) => Some(&e.code),
+            _ => None,
+        }
+    }
+
+    pub fn is_unauthenticated(&self) -> bool {
+        matches!(self, ApiError::Status(401, _))
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Network(e) => write!(f, "the server can not be reached ({})", e),
+            ApiError::Status(_, Some(e)) => f.write_str(&e.message),
+            ApiError::Status(status, None) => write!(f, "the server answered {}", status),
+            ApiError::Decode(e) => write!(f, "unexpected response ({})", e),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
@@ -0,0 +1,102 @@
+//! Typed client of the task notes api, shared by the gui and the integration tests.
+//!
+//! `Client` has a method for every endpoint of the backend, taking and returning
+//! the types of the `model` crate. Requests go through a `Transport`: `fetch` in
+//! the browser (`web::WebTransport`) and `reqwest` natively (`native::NativeTransport`).
+#![warn(clippy::all, rust_2018_idioms)]
+
+use model::models::ChangeEvent;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+mod endpoints;
+pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+pub mod query;
+pub mod transport;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+
+pub use error::ApiError;
+pub use model::query::{ActivityQuery, DueFilter, ProjectQuery, SortBy, TaskListQuery, TaskQuery};
+pub use query::Query;
+pub use transport::{Method, Pending, Reply, Transport};
+
+/// The backend and the transport to reach it.
+#[derive(Clone)]
+pub struct Client {
+    /// Base url ending in `/`, the api lives under `api/`.
+    pub url: Arc<str>,
+    pub transport: Arc<dyn Transport>,
+    /// Whether the last request reached the server.
+    online: Arc<AtomicBool>,
+}
+
+impl Client {
+    pub fn new(url: &str, transport: Arc<dyn Transport>) -> Client {
+        let url = if url.ends_with('/') { url.to_owned() } else { format!("{}/", url) };
+        Client { url: url.into(), transport, online: Arc::new(AtomicBool::new(true)) }
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
+    /// Full url of `path` below the base url.
+    pub fn endpoint(&self, path: &str, query: &Query) -> String {
+        format!("{}{}{}", self.url, path, query.as_str())
+    }
+
+    /// Sends a request and decodes the json of a successful response.
+    async fn request<T: DeserializeOwned>(&self, method: Method, path: &str, query: &Query, body: Option<Value>) -> Result<T, ApiError> {
+        let reply = self.transport.request(method, self.endpoint(path, query), body).await;
+        self.online.store(reply.is_ok(), Ordering::Relaxed);
+        let reply = reply.map_err(ApiError::Network)?;
+        if !(200..300).contains(&reply.status) {
+            return Err(ApiError::Status(reply.status, serde_json::from_str(&reply.body).ok()));
+        }
+        serde_json::from_str(&reply.body).map_err(|e| ApiError::Decode(e.to_string()))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &Query) -> Result<T, ApiError> {
+        self.request(Method::Get, path, query, None).await
+    }
+
+    async fn delete<T: DeserializeOwned>(&self, path: &str, query: &Query) -> Result<T, ApiError> {
+        self.request(Method::Delete, path, query, None).await
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T, ApiError> {
+        self.request(Method::Post, path, &Query::new(), Some(serde_json::to_value(body).unwrap())).await
+    }
+
+    async fn patch<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T, ApiError> {
+        self.request(Method::Patch, path, &Query::new(), Some(serde_json::to_value(body).unwrap())).await
+    }
+
+    /// Listens to the change events of the caller's projects for the lifetime
+    /// of the transport, see `/api/events`.
+    pub fn subscribe(&self, mut on_event: impl FnMut(ChangeEvent) + Send + 'static) {
+        let url = self.endpoint("api/events", &Query::new());
+        self.transport.subscribe(url, Box::new(move |data| {
+            match serde_json::from_str::<ChangeEvent>(&data) {
+                Ok(event) => on_event(event),
+                Err(e) => log::warn!("Dropped an event that does not decode: {}", e),
+            }
+        }));
+    }
+
+    /// See `Transport::sign_in`.
+    pub fn sign_in(&self, token: &str) {
+        self.transport.sign_in(&self.url, token);
+    }
+
+    /// See `Transport::sign_out`.
+    pub fn sign_out(&self) {
+        self.transport.sign_out(&self.url);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::transport::{Method, Pending, Reply, Transport};
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Pause before a dropped event stream is opened again.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Talks to the backend with `reqwest` on a runtime of its own, so requests
+/// never block the ui thread. Session cookies of `/api/login` are kept.
+pub struct NativeTransport {
+    client: reqwest::Client,
+    runtime: tokio::runtime::Runtime,
+    /// Api token sent as `Authorization: Bearer`, see `/api/token`.
+    token: Arc<RwLock<Option<String>>>,
+}
+
+impl NativeTransport {
+    pub fn new(token: Option<String>) -> std::io::Result<NativeTransport> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("task-notes-http")
+            .enable_all()
+            .build()?;
+        Ok(NativeTransport {
+            client: reqwest::Client::builder()
+                .cookie_store(true)
+                .build()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?,
+            runtime,
+            token: Arc::new(RwLock::new(token)),
+        })
+    }
+}
+
+fn builder(
+    client: &reqwest::Client,
+    token: &RwLock<Option<String>>,
+    method: reqwest::Method,
+    url: &str,
+) -> reqwest::RequestBuilder {
+    let request = client.request(method, url);
+    match *token.read().unwrap() {
+        Some(ref token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+impl Transport for NativeTransport {
+    fn request(&self, method: Method, url: String, body: Option<Value>) -> Pending<Result<Reply, String>> {
+        let method = match method {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+            Method::Patch => reqwest::Method::PATCH,
+            Method::Delete => reqwest::Method::DELETE,
+        };
+        let mut request = builder(&self.client, &self.token, method, &url);
+        if let Some(body) = body {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string());
+        }
+        Box::pin(async move {
+            let response = request.send().await.map_err(|e| e.to_string())?;
+            let status = response.status().as_u16();
+            let body = response.text().await.map_err(|e| e.to_string())?;
+            Ok(Reply { status, body })
+        })
+    }
+
+    fn spawn(&self, task: Pending<()>) {
+        self.runtime.spawn(task);
+    }
+
+    fn subscribe(&self, url: String, mut on_message: Box<dyn FnMut(String) + Send>) {
+        let client = self.client.clone();
+        let token = self.token.clone();
+        self.runtime.spawn(async move {
+            loop {
+                // built anew each time, the token may have changed since
+                let sent = builder(&client, &token, reqwest::Method::GET, &url).send().await;
+                let mut response = match sent {
+                    Ok(response) if response.status().is_success() => response,
+                    Ok(response) => {
+                        log::warn!("Subscribing to {} failed: {}", url, response.status());
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                    Err(e) => {
+                        log::warn!("Subscribing to {} failed: {}", url, e);
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                };
+                let mut buffer: Vec<u8> = Vec::new();
+                while let Ok(Some(chunk)) = response.chunk().await {
+                    buffer.extend_from_slice(&chunk);
+                    // messages end with a blank line, comments carry no `data:`
+                    while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                        let message: Vec<u8> = buffer.drain(..end + 2).collect();
+                        let data: Vec<&str> = std::str::from_utf8(&message)
+                            .unwrap_or_default()
+                            .lines()
+                            .filter_map(|line| line.strip_prefix("data: "))
+                            .collect();
+                        if !data.is_empty() {
+                            on_message(data.join("\n"));
+                        }
+                    }
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+    }
+
+    fn takes_token(&self) -> bool {
+        true
+    }
+
+    fn sign_in(&self, _server_url: &str, token: &str) {
+        *self.token.write().unwrap() = Some(token.trim().to_owned());
+    }
+
+    fn sign_out(&self, _server_url: &str) {
+        // tokens outlive the session, `/api/logout` has nothing to end
+        *self.token.write().unwrap() = None;
+    }
+}
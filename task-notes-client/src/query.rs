@@ -0,0 +1,50 @@
+use serde::Serialize;
+
+/// Percent-encodes a query parameter value.
+pub fn encode_query(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(b as char),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// Query string of a request, values are encoded as they are added.
+#[derive(Debug, Clone, Default)]
+pub struct Query(String);
+
+impl Query {
+    pub fn new() -> Query {
+        Query::default()
+    }
+
+    pub fn with(mut self, key: &str, value: impl ToString) -> Query {
+        self.0.push(if self.0.is_empty() { '?' } else { '&' });
+        self.0.push_str(key);
+        self.0.push('=');
+        self.0.push_str(&encode_query(&value.to_string()));
+        self
+    }
+
+    /// The query string of one of the typed queries of `model::query`.
+    pub fn encode<T: Serialize>(params: &T) -> Query {
+        let encoded = serde_urlencoded::to_string(params).expect("typed queries are flat key value pairs");
+        if encoded.is_empty() { Query::new() } else { Query(format!("?{}", encoded)) }
+    }
+
+    /// Like `with`, leaves the parameter out for `None`.
+    pub fn with_opt(self, key: &str, value: Option<impl ToString>) -> Query {
+        match value {
+            Some(value) => self.with(key, value),
+            None => self,
+        }
+    }
+
+    /// The query string with its leading `?`, empty without parameters.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
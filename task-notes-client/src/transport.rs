@@ -0,0 +1,61 @@
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+
+/// A future the transport runs, futures of `fetch` can not leave the browser's thread.
+#[cfg(target_arch = "wasm32")]
+pub type Pending<T> = Pin<Box<dyn Future<Output = T>>>;
+#[cfg(not(target_arch = "wasm32"))]
+pub type Pending<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Status and body of a response, error responses included.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+    Patch,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Patch => "PATCH",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+/// Carries the client's requests to the backend, with `fetch` on the web
+/// (`WebTransport`) and `reqwest` on a background runtime natively (`NativeTransport`).
+pub trait Transport: Send + Sync {
+    /// Sends a request with an optional json body, fails only if the server
+    /// could not be reached.
+    fn request(&self, method: Method, url: String, body: Option<Value>) -> Pending<Result<Reply, String>>;
+
+    /// Runs `task` to completion in the background.
+    fn spawn(&self, task: Pending<()>);
+
+    /// Listens to the server-sent event stream at `url` for the lifetime of
+    /// the app and hands the data of every message to `on_message`.
+    fn subscribe(&self, url: String, on_message: Box<dyn FnMut(String) + Send>);
+
+    /// Whether signing in takes an api token rather than the backend's login page.
+    fn takes_token(&self) -> bool;
+
+    /// Starts signing in, the browser leaves for the backend's `/login` and
+    /// comes back with a session while native clients switch to `token`.
+    fn sign_in(&self, server_url: &str, token: &str);
+
+    /// Ends the session, the browser leaves for `/api/logout` while native
+    /// clients forget their token.
+    fn sign_out(&self, server_url: &str);
+}
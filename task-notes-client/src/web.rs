@@ -0,0 +1,78 @@
+use crate::transport::{Method, Pending, Reply, Transport};
+use web_sys::{EventSource, EventSourceInit, MessageEvent};
+use web_sys::{Request, RequestCredentials, RequestInit, RequestMode, Response};
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+/// Talks to the backend with the browser's `fetch`, sending the session cookie along.
+pub struct WebTransport;
+
+async fn fetch(method: Method, url: String, body: Option<Value>) -> Result<Reply, JsValue> {
+    let mut opts = RequestInit::new();
+    opts.method(method.as_str());
+    opts.mode(RequestMode::Cors);
+    opts.credentials(RequestCredentials::Include);
+    if let Some(ref body) = body {
+        opts.body(Some(&JsValue::from_str(&body.to_string())));
+    }
+    let request = Request::new_with_str_and_init(&url, &opts)?;
+    if body.is_some() {
+        request.headers().set("content-type", "application/json")?;
+    }
+    let window = web_sys::window().unwrap();
+    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
+    let resp: Response = resp_value.dyn_into()?;
+    // Convert this other `Promise` into a rust `Future`.
+    let text = JsFuture::from(resp.text()?).await?;
+    Ok(Reply { status: resp.status(), body: text.as_string().unwrap_or_default() })
+}
+
+impl Transport for WebTransport {
+    fn request(&self, method: Method, url: String, body: Option<Value>) -> Pending<Result<Reply, String>> {
+        Box::pin(async move { fetch(method, url, body).await.map_err(|e| format!("{:?}", e)) })
+    }
+
+    fn spawn(&self, task: Pending<()>) {
+        wasm_bindgen_futures::spawn_local(task);
+    }
+
+    fn subscribe(&self, url: String, mut on_message: Box<dyn FnMut(String) + Send>) {
+        let mut init = EventSourceInit::new();
+        init.with_credentials(true);
+        let source = match EventSource::new_with_event_source_init_dict(&url, &init) {
+            Ok(source) => source,
+            Err(e) => {
+                log::warn!("Subscribing to {} failed: {:?}", url, e);
+                return;
+            }
+        };
+        // the browser reconnects on its own when the connection drops
+        let on_event = Closure::<dyn FnMut(MessageEvent)>::new(move |message: MessageEvent| {
+            if let Some(data) = message.data().as_string() {
+                on_message(data);
+            }
+        });
+        source.set_onmessage(Some(on_event.as_ref().unchecked_ref()));
+        on_event.forget();
+    }
+
+    fn takes_token(&self) -> bool {
+        false
+    }
+
+    fn sign_in(&self, server_url: &str, _token: &str) {
+        navigate(&format!("{}login", server_url));
+    }
+
+    fn sign_out(&self, server_url: &str) {
+        navigate(&format!("{}api/logout", server_url));
+    }
+}
+
+fn navigate(url: &str) {
+    let window = web_sys::window().unwrap();
+    if let Err(e) = window.location().set_href(url) {
+        log::warn!("Opening {} failed: {:?}", url, e);
+    }
+}
@@ -0,0 +1,135 @@
+//! Runs the client against a live backend, so the client and the routes can not
+//! drift apart. Start the backend and run
+//! `TASK_NOTES_URL=http://localhost:8180/ cargo test -p client -- --ignored`.
+use client::native::NativeTransport;
+use client::{ApiError, Client, ProjectQuery, TaskQuery};
+use model::models::{AppUser, Credentials, InviteMember, NewProject, NewTask, PatchProject, PatchTask, ProjectRole, TrashItem};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn client() -> Client {
+    let url = std::env::var("TASK_NOTES_URL").unwrap_or_else(|_| "http://localhost:8180/".to_owned());
+    Client::new(&url, Arc::new(NativeTransport::new(None).unwrap()))
+}
+
+/// Runs a test on a runtime of its own, the transport's runtime can not be
+/// dropped from within async code.
+fn run<F: Future>(test: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(test)
+}
+
+/// An email no other test run used.
+fn fresh_email() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    format!("client-test-{}@example.com", nanos)
+}
+
+/// Registers a fresh account, tests do not see each other's projects.
+async fn sign_up(client: &Client) -> AppUser {
+    let credentials = Credentials {
+        email: fresh_email(),
+        password: "correct horse battery staple".to_owned(),
+    };
+    client.register(&credentials).await.expect("registering failed")
+}
+
+#[test]
+#[ignore = "needs a running backend"]
+fn requests_without_a_session_are_unauthenticated() {
+    let client = client();
+    let result = run(client.get_user());
+    assert!(matches!(result, Err(ref e) if e.is_unauthenticated()), "{:?}", result);
+}
+
+#[test]
+#[ignore = "needs a running backend"]
+fn projects_round_trip() {
+    let client = client();
+    run(async {
+        let user = sign_up(&client).await;
+        assert_eq!(client.get_user().await.unwrap().id, user.id);
+
+        let project = NewProject { title: "Groceries".to_owned(), owner_id: user.id, priority: None };
+        let created = client.create_project(&project).await.unwrap();
+        assert_eq!(created.title, "Groceries");
+        let listed = client.get_projects(&ProjectQuery::default()).await.unwrap();
+        assert!(listed.items.iter().any(|p| p.id == created.id));
+
+        let rename = PatchProject {
+            id: created.id,
+            title: Some("Shopping".to_owned()),
+            version: Some(created.version),
+        };
+        let renamed = client.edit_project(&rename).await.unwrap();
+        assert_eq!(renamed.title, "Shopping");
+        // the same edit again builds on a version that is gone by now
+        let stale = client.edit_project(&rename).await;
+        assert_eq!(stale.as_ref().err().and_then(ApiError::code), Some("conflict"), "{:?}", stale);
+
+        client.delete_project(created.id).await.unwrap();
+        let trash = client.get_trash().await.unwrap();
+        assert!(trash.projects.iter().any(|p| p.id == created.id));
+        client.restore(&TrashItem::Project(created.id)).await.unwrap();
+        assert_eq!(client.get_project(created.id).await.unwrap().deleted_at, None);
+    });
+}
+
+#[test]
+#[ignore = "needs a running backend"]
+fn registering_an_invited_email_does_not_take_the_invite() {
+    let owner = client();
+    let stranger = client();
+    run(async {
+        let user = sign_up(&owner).await;
+        let project = NewProject { title: "Secrets".to_owned(), owner_id: user.id, priority: None };
+        let project = owner.create_project(&project).await.unwrap();
+        let email = fresh_email();
+        let invite = InviteMember { project_id: project.id, email: email.clone(), role: ProjectRole::Editor };
+        owner.invite_member(&invite).await.unwrap();
+
+        let credentials = Credentials { email, password: "correct horse battery staple".to_owned() };
+        let registered = stranger.register(&credentials).await;
+        assert_eq!(registered.as_ref().err().and_then(ApiError::code), Some("conflict"), "{:?}", registered);
+        let login = stranger.login(&credentials).await;
+        assert!(matches!(login, Err(ref e) if e.is_unauthenticated()), "{:?}", login);
+        let shared = stranger.get_project(project.id).await;
+        assert!(matches!(shared, Err(ref e) if e.is_unauthenticated()), "{:?}", shared);
+    });
+}
+
+#[test]
+#[ignore = "needs a running backend"]
+fn tasks_round_trip() {
+    let client = client();
+    run(async {
+        let user = sign_up(&client).await;
+        let project = NewProject { title: "Chores".to_owned(), owner_id: user.id, priority: None };
+        let project = client.create_project(&project).await.unwrap();
+        for title in ["Dishes", "Laundry", "Vacuum"] {
+            let task = NewTask {
+                title: title.to_owned(),
+                project_id: project.id,
+                task_list_id: None,
+                position: None,
+                due_at: None,
+                remind_at: None,
+                notes: String::new(),
+            };
+            client.create_task(&task).await.unwrap();
+        }
+
+        let first = client.get_tasks(&TaskQuery { limit: Some(2), ..TaskQuery::project(project.id) }).await.unwrap();
+        assert_eq!(first.items.len(), 2);
+        let rest = client.get_tasks(&TaskQuery { cursor: first.next_cursor, ..TaskQuery::project(project.id) }).await.unwrap();
+        assert_eq!(rest.items.len(), 1);
+
+        let done = PatchTask { id: rest.items[0].id, completed: Some(true), ..Default::default() };
+        assert!(client.edit_task(&done).await.unwrap().completed);
+        let open = TaskQuery { completed: Some(false), ..TaskQuery::project(project.id) };
+        assert_eq!(client.get_tasks(&open).await.unwrap().items.len(), 2);
+
+        let hits = client.search("laundry", None).await.unwrap();
+        assert!(hits.iter().any(|h| h.title == "Laundry"), "{:?}", hits);
+    });
+}
@@ -1,111 +1,356 @@
+use crate::api::error::{Failure, Resource, Retry};
+use crate::api::sync::{Mutation, Outbox};
 use crate::api::Update;
+use client::{ApiError, Client, Pending};
+use std::collections::HashMap;
 use std::sync::mpsc::Sender;
-use model::models::{PatchProject, NewProject, NewTask};
+use std::sync::{Arc, Mutex};
+use model::models::{PatchProject, PatchTask, NewProject, NewTask, ProjectOrder, MoveTask};
+use model::models::{NewTaskList, PatchTaskList, TaskListOrder};
+use model::models::{ChecklistOrder, NewChecklistItem, PatchChecklistItem};
+use model::models::{NewLabel, TaskLabel, TrashItem};
+use model::query::Cursor;
 
 #[derive(Clone)]
 pub struct Action {
     pub sender: Option<Sender<Update>>,
-    pub server_url: std::sync::Arc<str>
+    pub server: Client,
+    /// Woken once an update arrived, the ui would wait for the next input otherwise.
+    pub ctx: egui::Context,
+    /// Project and task changes on their way to the server.
+    pub outbox: Arc<Mutex<Outbox>>,
+    /// Requests in flight by what they load or change.
+    pub loading: Arc<Mutex<HashMap<Resource, usize>>>,
+}
+
+/// Counts a request as loading for as long as it lives.
+struct Loading {
+    counts: Arc<Mutex<HashMap<Resource, usize>>>,
+    resource: Resource,
+}
+
+impl Loading {
+    fn start(counts: &Arc<Mutex<HashMap<Resource, usize>>>, resource: Resource) -> Loading {
+        *counts.lock().unwrap().entry(resource).or_default() += 1;
+        Loading { counts: counts.clone(), resource }
+    }
+}
+
+impl Drop for Loading {
+    fn drop(&mut self) {
+        if let Some(count) = self.counts.lock().unwrap().get_mut(&self.resource) {
+            *count -= 1;
+        }
+    }
 }
 
 impl Action {
-    pub fn get_projects(&self) {
-        let s = self.sender.as_ref().unwrap().clone();
-        let server = self.server_url.clone();
-        wasm_bindgen_futures::spawn_local(async move {
-            match crate::api::get_projects(&server).await {
-                Some(u) => {
-                    s.send(u).unwrap();
-                }
-                _ => {}
-            }
-        });
+    pub fn is_loading(&self, resource: Resource) -> bool {
+        self.loading.lock().unwrap().get(&resource).is_some_and(|&count| count > 0)
+    }
+
+    /// Runs a read in the background and hands its update to the ui.
+    fn load(&self, resource: Resource, request: Pending<Result<Update, ApiError>>) {
+        self.spawn(resource, None, request);
+    }
+
+    /// Like `load` for a change, `retry` sends it again should it fail.
+    fn change(&self, resource: Resource, retry: impl Fn(&Action) + Send + Sync + 'static, request: Pending<Result<Update, ApiError>>) {
+        self.spawn(resource, Some(Arc::new(retry)), request);
     }
 
-    pub fn get_tasks(&self, project_id: i32) {
-        let s = self.sender.as_ref().unwrap().clone();
-        let server = self.server_url.clone();
-        wasm_bindgen_futures::spawn_local(async move {
-            match crate::api::get_tasks(&server, project_id as usize).await {
-                Some(u) => {
-                    s.send(u).unwrap();
+    fn spawn(&self, resource: Resource, retry: Option<Retry>, request: Pending<Result<Update, ApiError>>) {
+        let sender = self.sender.as_ref().unwrap().clone();
+        let loading = Loading::start(&self.loading, resource);
+        let ctx = self.ctx.clone();
+        self.server.transport.spawn(Box::pin(async move {
+            let result = request.await;
+            drop(loading);
+            let _ = sender.send(match result {
+                Ok(u) => u,
+                Err(e) if e.is_unauthenticated() => Update::SignedOut,
+                Err(error) => Update::Error(Failure { resource, error, retry }),
+            });
+            ctx.request_repaint();
+        }));
+    }
+
+    /// Shows `mutation` right away and sends it once the changes before it went through.
+    fn queue(&self, mutation: Mutation) {
+        if let Some(ref sender) = self.sender {
+            let _ = sender.send(Update::Pending(mutation.clone()));
+        }
+        self.outbox.lock().unwrap().push(mutation);
+        self.sync();
+    }
+
+    /// Sends the queued changes in order, stops at the first one that does not
+    /// reach the server and leaves it for the next attempt.
+    pub fn sync(&self) {
+        if !self.outbox.lock().unwrap().start_flush() {
+            return;
+        }
+        let sender = self.sender.as_ref().unwrap().clone();
+        let server = self.server.clone();
+        let outbox = self.outbox.clone();
+        let loading = self.loading.clone();
+        let ctx = self.ctx.clone();
+        self.server.transport.spawn(Box::pin(async move {
+            loop {
+                let mutation = match outbox.lock().unwrap().front() {
+                    Some(mutation) => mutation,
+                    None => break,
+                };
+                let resource = mutation.resource();
+                let sent = Loading::start(&loading, resource);
+                let result = mutation.send(&server).await;
+                drop(sent);
+                match result {
+                    Err(ApiError::Network(_)) => break,
+                    Err(ref e) if e.is_unauthenticated() => {
+                        let _ = sender.send(Update::SignedOut);
+                        break;
+                    }
+                    _ => {}
                 }
-                _ => {}
+                // turned down changes are dropped, the server would answer them the same way again
+                let dropped = outbox.lock().unwrap().confirm(result.as_ref().ok());
+                let _ = sender.send(match result {
+                    Ok(u) => u,
+                    Err(error) => {
+                        // retrying sends the changes that built on this one again too
+                        let retry: Retry = Arc::new(move |a: &Action| {
+                            a.queue(mutation.clone());
+                            dropped.iter().for_each(|m| a.queue(m.clone()));
+                        });
+                        Update::Error(Failure { resource, error, retry: Some(retry) })
+                    }
+                });
+                ctx.request_repaint();
             }
-        });
+            outbox.lock().unwrap().stop_flush();
+            ctx.request_repaint();
+        }));
+    }
+
+    /// Checks whether the server can be reached again and sends what piled up meanwhile.
+    pub fn reconnect(&self) {
+        self.get_user();
+        self.sync();
+    }
+
+    pub fn get_user(&self) {
+        let server = self.server.clone();
+        self.load(Resource::User, Box::pin(async move { crate::api::get_user(&server).await }));
     }
 
-    pub fn create_task(&self, title: &str, project_id: i32) {
-        let s = self.sender.as_ref().unwrap().clone();
-        let server = self.server_url.clone();
+    /// Signs in and loads the user's projects, the browser leaves for the
+    /// backend's login page instead.
+    pub fn sign_in(&self, token: &str) {
+        self.server.sign_in(token);
+        self.get_user();
+        self.get_projects(None);
+    }
+
+    pub fn sign_out(&self) {
+        self.server.sign_out();
+        if let Some(ref sender) = self.sender {
+            let _ = sender.send(Update::SignedOut);
+        }
+    }
+
+    /// Loads the first page of the projects, or the page after `cursor`.
+    pub fn get_projects(&self, cursor: Option<Cursor>) {
+        let server = self.server.clone();
+        self.load(Resource::Projects, Box::pin(async move { crate::api::get_projects(&server, cursor).await }));
+    }
+
+    /// Loads the first page of a project's tasks, or the page after `cursor`.
+    pub fn get_tasks(&self, project_id: i32, cursor: Option<Cursor>) {
+        let server = self.server.clone();
+        self.load(Resource::Tasks, Box::pin(async move { crate::api::get_tasks(&server, project_id, cursor).await }));
+    }
+
+    pub fn create_task(&self, title: &str, project_id: i32, task_list_id: Option<i32>) {
         let task = NewTask {
             title: title.to_string(),
-            task_list_id: None,
-            project_id
+            task_list_id,
+            project_id,
+            position: None,
+            due_at: None,
+            remind_at: None,
+            notes: String::new(),
         };
-        wasm_bindgen_futures::spawn_local(async move {
-            match crate::api::create_task(&server, &task).await {
-                Some(u) => {
-                    s.send(u).unwrap();
-                }
-                _ => {}
-            }
-        });
+        let local_id = self.outbox.lock().unwrap().next_local_id();
+        self.queue(Mutation::CreateTask { local_id, task });
     }
 
     pub fn create_project(&self, title: &str) {
-        let s = self.sender.as_ref().unwrap().clone();
-        let server = self.server_url.clone();
         let project = NewProject {
             title: title.to_string(),
-            owner_id: 1
+            owner_id: 1,
+            priority: None,
         };
-        wasm_bindgen_futures::spawn_local(async move {
-            match crate::api::create_project(&server, &project).await {
-                Some(u) => {
-                    s.send(u).unwrap();
-                }
-                _ => {}
-            }
-        });
+        let local_id = self.outbox.lock().unwrap().next_local_id();
+        self.queue(Mutation::CreateProject { local_id, project });
+    }
+
+    pub fn restore(&self, item: TrashItem) {
+        let server = self.server.clone();
+        let retry = move |a: &Action| a.restore(item);
+        self.change(Resource::Trash, retry, Box::pin(async move { crate::api::restore(&server, &item).await }));
     }
 
     pub fn delete_project(&self, project_id: i32) {
-        let s = self.sender.as_ref().unwrap().clone();
-        let server = self.server_url.clone();
-        wasm_bindgen_futures::spawn_local(async move {
-            match crate::api::delete_project(&server, project_id).await {
-                Some(u) => {
-                    s.send(u).unwrap();
-                }
-                _ => {}
-            }
-        });
+        self.queue(Mutation::DeleteProject(project_id));
     }
 
     pub fn edit_project(&self, project: PatchProject) {
-        let s = self.sender.as_ref().unwrap().clone();
-        let server = self.server_url.clone();
-        wasm_bindgen_futures::spawn_local(async move {
-            match crate::api::edit_project(&server, &project).await {
-                Some(u) => {
-                    s.send(u).unwrap();
-                }
-                _ => {}
-            }
-        });
+        self.queue(Mutation::EditProject(project));
     }
 
     pub fn delete_task(&self, task_id: i32) {
-        let s = self.sender.as_ref().unwrap().clone();
-        let server = self.server_url.clone();
-        wasm_bindgen_futures::spawn_local(async move {
-            match crate::api::delete_task(&server, task_id).await {
-                Some(u) => {
-                    s.send(u).unwrap();
-                }
-                _ => {}
-            }
-        });
+        self.queue(Mutation::DeleteTask(task_id));
+    }
+
+    pub fn edit_task(&self, task: PatchTask) {
+        self.queue(Mutation::EditTask(task));
     }
-}
\ No newline at end of file
+
+    pub fn get_task_lists(&self, project_id: i32) {
+        let server = self.server.clone();
+        self.load(Resource::TaskLists, Box::pin(async move { crate::api::get_task_lists(&server, project_id).await }));
+    }
+
+    pub fn create_task_list(&self, title: &str, project_id: i32) {
+        let server = self.server.clone();
+        let title = title.to_owned();
+        let task_list = NewTaskList {
+            title: title.clone(),
+            project_id,
+            position: None
+        };
+        let retry = move |a: &Action| a.create_task_list(&title, project_id);
+        self.change(Resource::TaskLists, retry, Box::pin(async move { crate::api::create_task_list(&server, &task_list).await }));
+    }
+
+    pub fn edit_task_list(&self, task_list: PatchTaskList) {
+        let server = self.server.clone();
+        let again = task_list.clone();
+        let retry = move |a: &Action| a.edit_task_list(again.clone());
+        self.change(Resource::TaskLists, retry, Box::pin(async move { crate::api::edit_task_list(&server, &task_list).await }));
+    }
+
+    pub fn delete_task_list(&self, task_list_id: i32) {
+        let server = self.server.clone();
+        let retry = move |a: &Action| a.delete_task_list(task_list_id);
+        self.change(Resource::TaskLists, retry, Box::pin(async move { crate::api::delete_task_list(&server, task_list_id).await }));
+    }
+
+    pub fn reorder_task_lists(&self, order: TaskListOrder) {
+        let server = self.server.clone();
+        let again = order.clone();
+        let retry = move |a: &Action| a.reorder_task_lists(again.clone());
+        self.change(Resource::TaskLists, retry, Box::pin(async move { crate::api::reorder_task_lists(&server, &order).await }));
+    }
+
+    pub fn reorder_projects(&self, order: ProjectOrder) {
+        let server = self.server.clone();
+        let again = order.clone();
+        let retry = move |a: &Action| a.reorder_projects(again.clone());
+        self.change(Resource::Projects, retry, Box::pin(async move { crate::api::reorder_projects(&server, &order).await }));
+    }
+
+    pub fn move_task(&self, m: MoveTask) {
+        self.queue(Mutation::MoveTask(m));
+    }
+
+    pub fn get_checklist_items(&self, project_id: i32) {
+        let server = self.server.clone();
+        self.load(Resource::Checklists, Box::pin(async move { crate::api::get_checklist_items(&server, project_id).await }));
+    }
+
+    pub fn create_checklist_item(&self, task_id: i32, title: &str) {
+        let server = self.server.clone();
+        let title = title.to_owned();
+        let item = NewChecklistItem {
+            task_id,
+            title: title.clone(),
+            position: None,
+        };
+        let retry = move |a: &Action| a.create_checklist_item(task_id, &title);
+        self.change(Resource::Checklists, retry, Box::pin(async move { crate::api::create_checklist_item(&server, &item).await }));
+    }
+
+    pub fn edit_checklist_item(&self, changes: PatchChecklistItem) {
+        let server = self.server.clone();
+        let again = changes.clone();
+        let retry = move |a: &Action| a.edit_checklist_item(again.clone());
+        self.change(Resource::Checklists, retry, Box::pin(async move { crate::api::edit_checklist_item(&server, &changes).await }));
+    }
+
+    pub fn delete_checklist_item(&self, item_id: i32) {
+        let server = self.server.clone();
+        let retry = move |a: &Action| a.delete_checklist_item(item_id);
+        self.change(Resource::Checklists, retry, Box::pin(async move { crate::api::delete_checklist_item(&server, item_id).await }));
+    }
+
+    pub fn reorder_checklist(&self, order: ChecklistOrder) {
+        let server = self.server.clone();
+        let again = order.clone();
+        let retry = move |a: &Action| a.reorder_checklist(again.clone());
+        self.change(Resource::Checklists, retry, Box::pin(async move { crate::api::reorder_checklist(&server, &order).await }));
+    }
+
+    pub fn get_labels(&self, project_id: i32) {
+        let server = self.server.clone();
+        self.load(Resource::Labels, Box::pin(async move { crate::api::get_labels(&server, project_id).await }));
+    }
+
+    pub fn create_label(&self, project_id: i32, name: &str, color: String) {
+        let server = self.server.clone();
+        let name = name.to_owned();
+        let label = NewLabel {
+            project_id,
+            name: name.clone(),
+            color: color.clone(),
+        };
+        let retry = move |a: &Action| a.create_label(project_id, &name, color.clone());
+        self.change(Resource::Labels, retry, Box::pin(async move { crate::api::create_label(&server, &label).await }));
+    }
+
+    pub fn delete_label(&self, label_id: i32) {
+        let server = self.server.clone();
+        let retry = move |a: &Action| a.delete_label(label_id);
+        self.change(Resource::Labels, retry, Box::pin(async move { crate::api::delete_label(&server, label_id).await }));
+    }
+
+    pub fn get_task_labels(&self, project_id: i32) {
+        let server = self.server.clone();
+        self.load(Resource::Labels, Box::pin(async move { crate::api::get_task_labels(&server, project_id).await }));
+    }
+
+    pub fn attach_label(&self, task_id: i32, label_id: i32) {
+        let server = self.server.clone();
+        let retry = move |a: &Action| a.attach_label(task_id, label_id);
+        self.change(Resource::Labels, retry, Box::pin(async move { crate::api::attach_label(&server, &TaskLabel { task_id, label_id }).await }));
+    }
+
+    pub fn detach_label(&self, task_id: i32, label_id: i32) {
+        let server = self.server.clone();
+        let retry = move |a: &Action| a.detach_label(task_id, label_id);
+        self.change(Resource::Labels, retry, Box::pin(async move { crate::api::detach_label(&server, &TaskLabel { task_id, label_id }).await }));
+    }
+
+    pub fn search(&self, q: &str) {
+        let server = self.server.clone();
+        let q = q.to_owned();
+        self.load(Resource::Search, Box::pin(async move { crate::api::search(&server, &q).await }));
+    }
+
+    /// Loads the newest page of a project's history, or the page after `cursor`.
+    pub fn get_activity(&self, project_id: i32, cursor: Option<Cursor>) {
+        let server = self.server.clone();
+        self.load(Resource::Activity, Box::pin(async move { crate::api::get_activity(&server, project_id, cursor).await }));
+    }
+}
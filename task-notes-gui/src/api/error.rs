@@ -0,0 +1,54 @@
+use crate::api::action::Action;
+use client::ApiError;
+use std::sync::Arc;
+
+/// What a request loads or changes, tells which parts of the ui are loading or failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    User,
+    Projects,
+    Tasks,
+    TaskLists,
+    Checklists,
+    Labels,
+    Search,
+    Activity,
+    Trash,
+}
+
+impl Resource {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Resource::User => "your account",
+            Resource::Projects => "projects",
+            Resource::Tasks => "tasks",
+            Resource::TaskLists => "task lists",
+            Resource::Checklists => "checklists",
+            Resource::Labels => "labels",
+            Resource::Search => "search results",
+            Resource::Activity => "history",
+            Resource::Trash => "the trash",
+        }
+    }
+}
+
+/// Sends a failed change again.
+pub type Retry = Arc<dyn Fn(&Action) + Send + Sync>;
+
+/// A request that failed, shown to the user until dismissed or retried.
+#[derive(Clone)]
+pub struct Failure {
+    pub resource: Resource,
+    pub error: ApiError,
+    /// Set for changes, reads are repeated by the ui anyway.
+    pub retry: Option<Retry>,
+}
+
+impl Failure {
+    pub fn message(&self) -> String {
+        match self.retry {
+            Some(_) => format!("Saving {} failed: {}", self.resource.describe(), self.error),
+            None => format!("Loading {} failed: {}", self.resource.describe(), self.error),
+        }
+    }
+}
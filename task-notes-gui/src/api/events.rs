@@ -0,0 +1,13 @@
+use crate::api::Update;
+use client::Client;
+use std::sync::mpsc::Sender;
+
+/// Subscribes to the server's change events and feeds them to `sender` as
+/// `Update::Event`, waking the ui for each.
+pub fn listen(server: &Client, sender: Sender<Update>, ctx: egui::Context) {
+    server.subscribe(move |event| {
+        if sender.send(Update::Event(event)).is_ok() {
+            ctx.request_repaint();
+        }
+    });
+}
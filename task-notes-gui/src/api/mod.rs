@@ -1,66 +1,212 @@
-use model::models::{Task, Project, NewProject, NewTask, PatchProject};
+use model::models::{Task, Project, NewProject, NewTask, PatchProject, PatchTask, ProjectOrder, MoveTask};
+use model::models::{TaskList, NewTaskList, PatchTaskList, TaskListOrder};
+use model::models::{Checklist, ChecklistItem, ChecklistOrder, NewChecklistItem, PatchChecklistItem};
+use model::models::{AppUser, Label, NewLabel, TaskLabel};
+use model::models::{Activity, ChangeEvent, ListResponse, SearchHit, Trash, TrashItem};
+use model::query::Cursor;
 use serde::{Serialize, Deserialize};
+use client::{ActivityQuery, ApiError, Client, ProjectQuery, TaskQuery};
+use error::Failure;
+use sync::Mutation;
 
 pub mod action;
-pub mod common;
+pub mod error;
+pub mod events;
+pub mod sync;
 
-
-#[derive(Serialize, Deserialize)]
 pub enum Update {
+    /// Every project, in their manual order.
     ProjectList(Vec<Project>),
+    /// Every task of the selected project.
     TaskList(Vec<Task>),
+    /// A page of the projects and the cursor it was loaded from, `None` for
+    /// the first page.
+    Projects(Option<Cursor>, ListResponse<Project>),
+    /// A page of a project's tasks and the cursor it was loaded from, `None`
+    /// for the first page.
+    Tasks(i32, Option<Cursor>, ListResponse<Task>),
     ProjectCreated(Project),
     ProjectDeleted(Project),
     TaskDeleted(Task),
-    ProjectChanged(PatchProject),
-    TaskCreated(Task)
+    ProjectChanged(Project),
+    TaskCreated(Task),
+    TaskChanged(Task),
+    TaskLists(Vec<TaskList>),
+    TaskListCreated(TaskList),
+    TaskListChanged(TaskList),
+    TaskListDeleted(TaskList),
+    ChecklistItems(Vec<ChecklistItem>),
+    Checklist(Checklist),
+    Labels(Vec<Label>),
+    LabelCreated(Label),
+    LabelDeleted(Label),
+    TaskLabels(Vec<TaskLabel>),
+    LabelAttached(TaskLabel),
+    LabelDetached(TaskLabel),
+    /// Hits for the query they answer, stale answers are dropped.
+    SearchResults(String, Vec<SearchHit>),
+    Restored(Trash),
+    /// A page of a project's history and the cursor it was loaded from,
+    /// `None` for the newest page.
+    Activity(i32, Option<Cursor>, ListResponse<Activity>),
+    /// Pushed by the server after someone changed one of our projects.
+    Event(ChangeEvent),
+    Conflict(Conflict),
+    /// The signed in user.
+    User(AppUser),
+    /// The server turned a request down for want of a session.
+    SignedOut,
+    /// A change to show right away, it goes to the server once it can be reached.
+    Pending(Mutation),
+    /// The server created the project shown under the local id so far.
+    ProjectSynced(i32, Project),
+    /// The server created the task shown under the local id so far.
+    TaskSynced(i32, Task),
+    /// A request failed, the user gets to see why.
+    Error(Failure),
+}
+
+/// An edit the server turned down because someone else changed the project or
+/// task first, with the changes we sent and the server's current state.
+#[derive(Serialize, Deserialize)]
+pub enum Conflict {
+    Project { mine: PatchProject, theirs: Project },
+    Task { mine: PatchTask, theirs: Task },
+}
+
+pub async fn get_user(server: &Client) -> Result<Update, ApiError> {
+    Ok(Update::User(server.get_user().await?))
+}
+
+pub async fn get_projects(server: &Client, cursor: Option<Cursor>) -> Result<Update, ApiError> {
+    let v = server.get_projects(&ProjectQuery { cursor, ..Default::default() }).await?;
+    Ok(Update::Projects(cursor, v))
+}
+
+pub async fn edit_project(server: &Client, changes: &PatchProject) -> Result<Update, ApiError> {
+    match server.edit_project(changes).await {
+        Ok(v) => Ok(Update::ProjectChanged(v)),
+        Err(e) if e.code() == Some("conflict") => {
+            let theirs = server.get_project(changes.id).await?;
+            Ok(Update::Conflict(Conflict::Project { mine: changes.clone(), theirs }))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn reorder_projects(server: &Client, order: &ProjectOrder) -> Result<Update, ApiError> {
+    Ok(Update::ProjectList(server.reorder_projects(order).await?))
 }
 
-pub async fn get_projects(server_url: &str) -> Option<Update> {
-    let js_value = common::get_json(format!("{}api/project?ownerId=1", server_url)).await.ok()?;
-    let v = serde_wasm_bindgen::from_value(js_value).ok()?;
-    Some(Update::ProjectList(v))
+pub async fn delete_project(server: &Client, project_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::ProjectDeleted(server.delete_project(project_id).await?))
 }
 
-pub async fn edit_project(server_url: &str, changes: &PatchProject) -> Option<Update> {
-    let data = serde_json::to_string(changes).unwrap();
-    let data = serde_wasm_bindgen::to_value(&data).unwrap();
-    let js_value = common::patch_json(format!("{}api/project", server_url), &data).await.ok()?;
-    let v = serde_wasm_bindgen::from_value(js_value).ok()?;
-    Some(Update::ProjectChanged(v))
+pub async fn delete_task(server: &Client, task_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::TaskDeleted(server.delete_task(task_id).await?))
 }
 
+pub async fn restore(server: &Client, item: &TrashItem) -> Result<Update, ApiError> {
+    Ok(Update::Restored(server.restore(item).await?))
+}
+
+pub async fn create_project(server: &Client, project: &NewProject) -> Result<Update, ApiError> {
+    Ok(Update::ProjectCreated(server.create_project(project).await?))
+}
+
+pub async fn create_task(server: &Client, task: &NewTask) -> Result<Update, ApiError> {
+    Ok(Update::TaskCreated(server.create_task(task).await?))
+}
+
+pub async fn edit_task(server: &Client, changes: &PatchTask) -> Result<Update, ApiError> {
+    match server.edit_task(changes).await {
+        Ok(v) => Ok(Update::TaskChanged(v)),
+        Err(e) if e.code() == Some("conflict") => {
+            let theirs = server.get_task(changes.id).await?;
+            Ok(Update::Conflict(Conflict::Task { mine: changes.clone(), theirs }))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn move_task(server: &Client, m: &MoveTask) -> Result<Update, ApiError> {
+    Ok(Update::TaskList(server.move_task(m).await?))
+}
+
+pub async fn get_tasks(server: &Client, project_id: i32, cursor: Option<Cursor>) -> Result<Update, ApiError> {
+    let v = server.get_tasks(&TaskQuery { cursor, ..TaskQuery::project(project_id) }).await?;
+    Ok(Update::Tasks(project_id, cursor, v))
+}
+
+pub async fn get_task_lists(server: &Client, project_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::TaskLists(server.get_task_lists(project_id).await?))
+}
 
-pub async fn delete_project(server_url: &str, project_id: i32) -> Option<Update> {
-    let js_value = common::delete_json(format!("{}api/project?id={}", server_url, project_id)).await.ok()?;
-    let v = serde_wasm_bindgen::from_value(js_value).ok()?;
-    Some(Update::ProjectDeleted(v))
+pub async fn create_task_list(server: &Client, task_list: &NewTaskList) -> Result<Update, ApiError> {
+    Ok(Update::TaskListCreated(server.create_task_list(task_list).await?))
 }
 
-pub async fn delete_task(server_url: &str, task_id: i32) -> Option<Update> {
-    let js_value = common::delete_json(format!("{}api/task?id={}", server_url, task_id)).await.ok()?;
-    let v = serde_wasm_bindgen::from_value(js_value).ok()?;
-    Some(Update::TaskDeleted(v))
+pub async fn edit_task_list(server: &Client, changes: &PatchTaskList) -> Result<Update, ApiError> {
+    Ok(Update::TaskListChanged(server.edit_task_list(changes).await?))
 }
 
-pub async fn create_project(server_url: &str, project: &NewProject) -> Option<Update> {
-    let data = serde_json::to_string(project).unwrap();
-    let data = serde_wasm_bindgen::to_value(&data).unwrap();
-    let js_value = common::post_json(format!("{}api/project", server_url), &data).await.ok()?;
-    let v = serde_wasm_bindgen::from_value(js_value).ok()?;
-    Some(Update::ProjectCreated(v))
+pub async fn delete_task_list(server: &Client, task_list_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::TaskListDeleted(server.delete_task_list(task_list_id).await?))
 }
 
-pub async fn create_task(server_url: &str, task: &NewTask) -> Option<Update> {
-    let data = serde_json::to_string(task).unwrap();
-    let data = serde_wasm_bindgen::to_value(&data).unwrap();
-    let js_value = common::post_json(format!("{}api/task", server_url), &data).await.ok()?;
-    let v = serde_wasm_bindgen::from_value(js_value).ok()?;
-    Some(Update::TaskCreated(v))
+pub async fn reorder_task_lists(server: &Client, order: &TaskListOrder) -> Result<Update, ApiError> {
+    Ok(Update::TaskLists(server.reorder_task_lists(order).await?))
 }
 
-pub async fn get_tasks(server_url: &str, project_id: usize) -> Option<Update> {
-    let js_value = common::get_json(format!("{}api/task?projectId={}", server_url, project_id)).await.ok()?;
-    let v = serde_wasm_bindgen::from_value(js_value).ok()?;
-    Some(Update::TaskList(v))
-}
\ No newline at end of file
+pub async fn get_checklist_items(server: &Client, project_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::ChecklistItems(server.get_checklist_items(project_id).await?))
+}
+
+pub async fn create_checklist_item(server: &Client, item: &NewChecklistItem) -> Result<Update, ApiError> {
+    Ok(Update::Checklist(server.create_checklist_item(item).await?))
+}
+
+pub async fn edit_checklist_item(server: &Client, changes: &PatchChecklistItem) -> Result<Update, ApiError> {
+    Ok(Update::Checklist(server.edit_checklist_item(changes).await?))
+}
+
+pub async fn delete_checklist_item(server: &Client, item_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::Checklist(server.delete_checklist_item(item_id).await?))
+}
+
+pub async fn reorder_checklist(server: &Client, order: &ChecklistOrder) -> Result<Update, ApiError> {
+    Ok(Update::Checklist(server.reorder_checklist(order).await?))
+}
+
+pub async fn get_labels(server: &Client, project_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::Labels(server.get_labels(project_id).await?))
+}
+
+pub async fn create_label(server: &Client, label: &NewLabel) -> Result<Update, ApiError> {
+    Ok(Update::LabelCreated(server.create_label(label).await?))
+}
+
+pub async fn delete_label(server: &Client, label_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::LabelDeleted(server.delete_label(label_id).await?))
+}
+
+pub async fn get_task_labels(server: &Client, project_id: i32) -> Result<Update, ApiError> {
+    Ok(Update::TaskLabels(server.get_task_labels(project_id).await?))
+}
+
+pub async fn attach_label(server: &Client, attach: &TaskLabel) -> Result<Update, ApiError> {
+    Ok(Update::LabelAttached(server.attach_label(attach).await?))
+}
+
+pub async fn detach_label(server: &Client, detach: &TaskLabel) -> Result<Update, ApiError> {
+    Ok(Update::LabelDetached(server.detach_label(detach).await?))
+}
+
+pub async fn search(server: &Client, q: &str) -> Result<Update, ApiError> {
+    Ok(Update::SearchResults(q.to_owned(), server.search(q, None).await?))
+}
+
+pub async fn get_activity(server: &Client, project_id: i32, cursor: Option<Cursor>) -> Result<Update, ApiError> {
+    let v = server.get_activity(project_id, &ActivityQuery { limit: None, cursor }).await?;
+    Ok(Update::Activity(project_id, cursor, v))
+}
@@ -0,0 +1,392 @@
+//! Changes to projects and tasks waiting for the server, kept across restarts
+//! so the gui keeps working offline.
+use crate::api::error::Resource;
+use crate::api::{Conflict, Update};
+use chrono::Utc;
+use client::{ApiError, Client};
+use model::models::{MoveTask, NewProject, NewTask, PatchProject, PatchTask, Project, Task};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// A change the ui already shows, sent to the server in the order it was made.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Mutation {
+    CreateProject { local_id: i32, project: NewProject },
+    EditProject(PatchProject),
+    DeleteProject(i32),
+    CreateTask { local_id: i32, task: NewTask },
+    EditTask(PatchTask),
+    DeleteTask(i32),
+    MoveTask(MoveTask),
+}
+
+impl Mutation {
+    /// Sends the change, created projects and tasks come back as
+    /// `ProjectSynced` and `TaskSynced` with the id they had so far.
+    pub async fn send(&self, server: &Client) -> Result<Update, ApiError> {
+        match self {
+            Mutation::CreateProject { local_id, project } => match crate::api::create_project(server, project).await? {
+                Update::ProjectCreated(p) => Ok(Update::ProjectSynced(*local_id, p)),
+                u => Ok(u),
+            },
+            Mutation::EditProject(changes) => crate::api::edit_project(server, changes).await,
+            Mutation::DeleteProject(id) => crate::api::delete_project(server, *id).await,
+            Mutation::CreateTask { local_id, task } => match crate::api::create_task(server, task).await? {
+                Update::TaskCreated(t) => Ok(Update::TaskSynced(*local_id, t)),
+                u => Ok(u),
+            },
+            Mutation::EditTask(changes) => crate::api::edit_task(server, changes).await,
+            Mutation::DeleteTask(id) => crate::api::delete_task(server, *id).await,
+            Mutation::MoveTask(m) => crate::api::move_task(server, m).await,
+        }
+    }
+
+    pub fn resource(&self) -> Resource {
+        match self {
+            Mutation::CreateProject { .. } | Mutation::EditProject(_) | Mutation::DeleteProject(_) => Resource::Projects,
+            _ => Resource::Tasks,
+        }
+    }
+
+    /// The local id of the project or task the mutation creates.
+    fn created_id(&self) -> Option<i32> {
+        match self {
+            Mutation::CreateProject { local_id, .. } | Mutation::CreateTask { local_id, .. } => Some(*local_id),
+            _ => None,
+        }
+    }
+
+    /// Whether the mutation refers to one of the projects or tasks `ids`.
+    fn refers_to(&self, ids: &[i32]) -> bool {
+        match self {
+            Mutation::CreateProject { .. } => false,
+            Mutation::EditProject(changes) => ids.contains(&changes.id),
+            Mutation::DeleteProject(pid) => ids.contains(pid),
+            Mutation::CreateTask { task, .. } => ids.contains(&task.project_id),
+            Mutation::EditTask(changes) => ids.contains(&changes.id),
+            Mutation::DeleteTask(tid) => ids.contains(tid),
+            Mutation::MoveTask(m) => [Some(m.id), m.before, m.after].iter().flatten().any(|id| ids.contains(id)),
+        }
+    }
+
+    /// Points the mutation at the project `id` where it refers to `from`, and
+    /// has its edits build on `version`.
+    fn rebase_project(&mut self, from: i32, id: i32, version: i32) {
+        match self {
+            Mutation::EditProject(changes) if changes.id == from => {
+                changes.id = id;
+                changes.version = Some(version);
+            }
+            Mutation::DeleteProject(pid) if *pid == from => *pid = id,
+            Mutation::CreateTask { task, .. } if task.project_id == from => task.project_id = id,
+            _ => {}
+        }
+    }
+
+    /// Like `rebase_project` for the task `id`.
+    fn rebase_task(&mut self, from: i32, id: i32, version: i32) {
+        match self {
+            Mutation::EditTask(changes) if changes.id == from => {
+                changes.id = id;
+                changes.version = Some(version);
+            }
+            Mutation::DeleteTask(tid) if *tid == from => *tid = id,
+            Mutation::MoveTask(m) => {
+                if m.id == from {
+                    m.id = id;
+                }
+                if m.before == Some(from) {
+                    m.before = Some(id);
+                }
+                if m.after == Some(from) {
+                    m.after = Some(id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Has an edit of `task` build on the version a move handed out. Only if
+    /// the move was the one change since the version the edit builds on,
+    /// anything newer is someone else's and the server reports the conflict.
+    fn rebase_moved(&mut self, task: &Task) {
+        if let Mutation::EditTask(changes) = self {
+            if changes.id == task.id && changes.version == Some(task.version - 1) {
+                changes.version = Some(task.version);
+            }
+        }
+    }
+}
+
+/// The mutations the server has not confirmed yet, oldest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Outbox {
+    pending: VecDeque<Mutation>,
+    /// Created projects and tasks go by negative ids until the server assigns theirs.
+    last_local_id: i32,
+    #[serde(skip)]
+    flushing: bool,
+}
+
+impl Outbox {
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn next_local_id(&mut self) -> i32 {
+        self.last_local_id -= 1;
+        self.last_local_id
+    }
+
+    pub fn push(&mut self, mutation: Mutation) {
+        self.pending.push_back(mutation);
+    }
+
+    /// Claims the outbox for a flush, `false` if one is running already.
+    pub fn start_flush(&mut self) -> bool {
+        !std::mem::replace(&mut self.flushing, true)
+    }
+
+    pub fn stop_flush(&mut self) {
+        self.flushing = false;
+    }
+
+    pub fn front(&self) -> Option<Mutation> {
+        self.pending.front().cloned()
+    }
+
+    /// Drops the oldest mutation once the server answered it and moves the
+    /// later ones onto the ids and versions it handed out. `None` means the
+    /// server turned it down, the later mutations that build on a project or
+    /// task it would have created are dropped as well and returned.
+    pub fn confirm(&mut self, update: Option<&Update>) -> Vec<Mutation> {
+        let answered = self.pending.pop_front();
+        let update = match update {
+            Some(update) => update,
+            None => return self.drop_dependents(answered),
+        };
+        for m in self.pending.iter_mut() {
+            match update {
+                Update::ProjectSynced(local_id, p) => m.rebase_project(*local_id, p.id, p.version),
+                Update::ProjectChanged(p) => m.rebase_project(p.id, p.id, p.version),
+                Update::TaskSynced(local_id, t) => m.rebase_task(*local_id, t.id, t.version),
+                Update::TaskChanged(t) => m.rebase_task(t.id, t.id, t.version),
+                // a move bumps the version of every task it shifted
+                Update::TaskList(tasks) => tasks.iter().for_each(|t| m.rebase_moved(t)),
+                // the prompt decides about the turned down edit, later ones
+                // build on theirs instead of failing the same way again
+                Update::Conflict(Conflict::Project { theirs, .. }) => m.rebase_project(theirs.id, theirs.id, theirs.version),
+                Update::Conflict(Conflict::Task { theirs, .. }) => m.rebase_task(theirs.id, theirs.id, theirs.version),
+                _ => {}
+            }
+        }
+        Vec::new()
+    }
+
+    fn drop_dependents(&mut self, rejected: Option<Mutation>) -> Vec<Mutation> {
+        let mut gone: Vec<i32> = rejected.and_then(|m| m.created_id()).into_iter().collect();
+        if gone.is_empty() {
+            return Vec::new();
+        }
+        let (dropped, kept): (Vec<Mutation>, Vec<Mutation>) = self.pending.drain(..).partition(|m| {
+            let depends = m.refers_to(&gone);
+            if depends {
+                // tasks created in a dropped project go too
+                gone.extend(m.created_id());
+            }
+            depends
+        });
+        self.pending = kept.into();
+        dropped
+    }
+}
+
+/// How a project created offline shows until the server has it.
+pub fn local_project(local_id: i32, project: &NewProject) -> Project {
+    let now = Utc::now();
+    Project {
+        id: local_id,
+        owner_id: project.owner_id,
+        title: project.title.clone(),
+        priority: project.priority.unwrap_or(i32::MAX),
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+        version: 0,
+    }
+}
+
+/// How a task created offline shows until the server has it.
+pub fn local_task(local_id: i32, task: &NewTask) -> Task {
+    let now = Utc::now();
+    Task {
+        id: local_id,
+        project_id: task.project_id,
+        task_list_id: task.task_list_id,
+        title: task.title.clone(),
+        completed: false,
+        position: task.position.unwrap_or(i32::MAX),
+        created_at: now,
+        updated_at: now,
+        due_at: task.due_at,
+        remind_at: task.remind_at,
+        notes: task.notes.clone(),
+        checklist_total: 0,
+        checklist_done: 0,
+        deleted_at: None,
+        version: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_project() -> NewProject {
+        NewProject { title: "Chores".to_owned(), owner_id: 1, priority: None }
+    }
+
+    fn new_task(project_id: i32) -> NewTask {
+        NewTask {
+            title: "Dishes".to_owned(),
+            project_id,
+            task_list_id: None,
+            position: None,
+            due_at: None,
+            remind_at: None,
+            notes: String::new(),
+        }
+    }
+
+    /// A task as the server hands it out.
+    fn task(id: i32, version: i32) -> Task {
+        Task { id, version, ..local_task(id, &new_task(3)) }
+    }
+
+    fn edit_task(id: i32, version: Option<i32>) -> Mutation {
+        Mutation::EditTask(PatchTask { id, completed: Some(true), version, ..Default::default() })
+    }
+
+    #[test]
+    fn local_ids_are_negative_and_unique() {
+        let mut outbox = Outbox::default();
+        let (a, b) = (outbox.next_local_id(), outbox.next_local_id());
+        assert!(a < 0 && b < 0 && a != b);
+    }
+
+    #[test]
+    fn synced_project_rebases_its_tasks_and_edits() {
+        let mut outbox = Outbox::default();
+        let pid = outbox.next_local_id();
+        let tid = outbox.next_local_id();
+        outbox.push(Mutation::CreateProject { local_id: pid, project: new_project() });
+        outbox.push(Mutation::CreateTask { local_id: tid, task: new_task(pid) });
+        let rename = PatchProject { id: pid, title: Some("Errands".to_owned()), version: Some(0) };
+        outbox.push(Mutation::EditProject(rename));
+        outbox.push(Mutation::DeleteProject(pid));
+
+        let project = Project { id: 7, version: 1, ..local_project(pid, &new_project()) };
+        assert!(outbox.confirm(Some(&Update::ProjectSynced(pid, project))).is_empty());
+        assert_eq!(outbox.len(), 3);
+        assert!(matches!(outbox.pending[0], Mutation::CreateTask { ref task, .. } if task.project_id == 7));
+        assert!(matches!(outbox.pending[1], Mutation::EditProject(ref p) if p.id == 7 && p.version == Some(1)));
+        assert!(matches!(outbox.pending[2], Mutation::DeleteProject(7)));
+    }
+
+    #[test]
+    fn synced_task_rebases_edits_and_moves_next_to_it() {
+        let mut outbox = Outbox::default();
+        let tid = outbox.next_local_id();
+        outbox.push(Mutation::CreateTask { local_id: tid, task: new_task(3) });
+        outbox.push(edit_task(tid, Some(0)));
+        outbox.push(Mutation::MoveTask(MoveTask { id: 5, before: Some(tid), ..Default::default() }));
+        outbox.push(Mutation::MoveTask(MoveTask { id: tid, after: Some(5), ..Default::default() }));
+        outbox.push(Mutation::DeleteTask(tid));
+
+        outbox.confirm(Some(&Update::TaskSynced(tid, task(9, 1))));
+        assert!(matches!(outbox.pending[0], Mutation::EditTask(ref t) if t.id == 9 && t.version == Some(1)));
+        assert!(matches!(outbox.pending[1], Mutation::MoveTask(ref m) if m.id == 5 && m.before == Some(9)));
+        assert!(matches!(outbox.pending[2], Mutation::MoveTask(ref m) if m.id == 9 && m.after == Some(5)));
+        assert!(matches!(outbox.pending[3], Mutation::DeleteTask(9)));
+    }
+
+    #[test]
+    fn edits_build_on_the_version_of_the_previous_one() {
+        let mut outbox = Outbox::default();
+        outbox.push(edit_task(4, Some(2)));
+        outbox.push(edit_task(4, Some(2)));
+        outbox.push(edit_task(5, Some(8)));
+        outbox.confirm(Some(&Update::TaskChanged(task(4, 3))));
+        assert!(matches!(outbox.pending[0], Mutation::EditTask(ref t) if t.id == 4 && t.version == Some(3)));
+        assert!(matches!(outbox.pending[1], Mutation::EditTask(ref t) if t.id == 5 && t.version == Some(8)));
+    }
+
+    #[test]
+    fn moves_hand_out_new_versions() {
+        let mut outbox = Outbox::default();
+        outbox.push(Mutation::MoveTask(MoveTask { id: 4, after: Some(5), ..Default::default() }));
+        outbox.push(edit_task(5, Some(1)));
+        outbox.confirm(Some(&Update::TaskList(vec![task(5, 2), task(4, 2)])));
+        assert!(matches!(outbox.pending[0], Mutation::EditTask(ref t) if t.id == 5 && t.version == Some(2)));
+    }
+
+    #[test]
+    fn moves_do_not_hide_concurrent_edits() {
+        let mut outbox = Outbox::default();
+        outbox.push(Mutation::MoveTask(MoveTask { id: 4, after: Some(5), ..Default::default() }));
+        outbox.push(edit_task(5, Some(1)));
+        outbox.push(edit_task(6, Some(1)));
+        // someone else edited 5 on top of the move, 6 did not move
+        outbox.confirm(Some(&Update::TaskList(vec![task(5, 3), task(4, 2), task(6, 1)])));
+        assert!(matches!(outbox.pending[0], Mutation::EditTask(ref t) if t.id == 5 && t.version == Some(1)));
+        assert!(matches!(outbox.pending[1], Mutation::EditTask(ref t) if t.id == 6 && t.version == Some(1)));
+    }
+
+    #[test]
+    fn edits_after_a_conflict_build_on_theirs() {
+        let mut outbox = Outbox::default();
+        outbox.push(edit_task(4, Some(1)));
+        outbox.push(edit_task(4, Some(1)));
+        outbox.push(edit_task(5, Some(1)));
+        let mine = PatchTask { id: 4, completed: Some(true), version: Some(1), ..Default::default() };
+        assert!(outbox.confirm(Some(&Update::Conflict(Conflict::Task { mine, theirs: task(4, 3) }))).is_empty());
+        assert!(matches!(outbox.pending[0], Mutation::EditTask(ref t) if t.id == 4 && t.version == Some(3)));
+        assert!(matches!(outbox.pending[1], Mutation::EditTask(ref t) if t.id == 5 && t.version == Some(1)));
+    }
+
+    #[test]
+    fn rejected_create_drops_what_builds_on_it() {
+        let mut outbox = Outbox::default();
+        let pid = outbox.next_local_id();
+        let tid = outbox.next_local_id();
+        outbox.push(Mutation::CreateProject { local_id: pid, project: new_project() });
+        outbox.push(Mutation::CreateTask { local_id: tid, task: new_task(pid) });
+        outbox.push(edit_task(4, Some(1)));
+        outbox.push(edit_task(tid, None));
+        outbox.push(Mutation::MoveTask(MoveTask { id: 4, before: Some(tid), ..Default::default() }));
+        outbox.push(Mutation::DeleteProject(pid));
+
+        let dropped = outbox.confirm(None);
+        // the task created in the project goes with it, and with the task everything pointing at it
+        assert_eq!(dropped.len(), 4);
+        assert!(matches!(dropped[0], Mutation::CreateTask { local_id, .. } if local_id == tid));
+        assert!(matches!(dropped[1], Mutation::EditTask(ref t) if t.id == tid));
+        assert!(matches!(dropped[2], Mutation::MoveTask(ref m) if m.before == Some(tid)));
+        assert!(matches!(dropped[3], Mutation::DeleteProject(id) if id == pid));
+        assert_eq!(outbox.len(), 1);
+        assert!(matches!(outbox.pending[0], Mutation::EditTask(ref t) if t.id == 4));
+    }
+
+    #[test]
+    fn rejected_edit_only_drops_itself() {
+        let mut outbox = Outbox::default();
+        outbox.push(edit_task(4, Some(1)));
+        outbox.push(edit_task(4, Some(1)));
+        assert!(outbox.confirm(None).is_empty());
+        assert_eq!(outbox.len(), 1);
+    }
+}
@@ -1,10 +1,64 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
 
-use model::models::{Project, Task, PatchProject};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use model::models::{Project, Task, PatchProject, PatchTask, ProjectOrder, MoveTask, MAX_NOTES_LEN};
+use model::models::{TaskList, PatchTaskList, TaskListOrder};
+use model::models::{ChecklistItem, ChecklistOrder, PatchChecklistItem};
+use model::models::{AppUser, Label, TaskLabel};
+use model::models::{Activity, ActivityAction, ChangeEvent, SearchHit, SearchKind, TrashItem};
+use model::query::Cursor;
 use crate::api::action::Action;
-use crate::api::Update;
+use crate::api::error::{Failure, Resource};
+use crate::api::sync::{self, Mutation, Outbox};
+use crate::api::{Conflict, Update};
+use crate::drag::DragList;
+use client::{ApiError, Client};
+
+/// Seconds the undo toast stays up after a deletion.
+const UNDO_SECONDS: f64 = 8.0;
+/// Seconds a failed read stays on screen, failed changes stay until dismissed.
+const ERROR_SECONDS: f64 = 8.0;
+/// Seconds between attempts to reach the server while offline.
+const RECONNECT_SECONDS: f64 = 5.0;
+/// Storage key of the projects and tasks shown while offline.
+const CACHE_KEY: &str = "cache";
+/// Storage key of the changes waiting for the server.
+const OUTBOX_KEY: &str = "outbox";
+
+/// What the app keeps across restarts to start up offline.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+struct Cache {
+    projects: Vec<Project>,
+    /// Tasks of the projects opened so far, by project id.
+    tasks: HashMap<i32, Vec<Task>>,
+}
+
+/// Offer to take a just deleted item out of the trash again.
+struct Undo {
+    item: TrashItem,
+    message: String,
+    /// `egui::InputState::time` at which the toast goes away.
+    until: f64,
+}
+
+/// A request that failed, shown above the undo toast.
+struct ErrorToast {
+    failure: Failure,
+    /// `egui::InputState::time` at which the toast goes away, if it does by itself.
+    until: Option<f64>,
+}
+
+/// What the central panel shows of the selected project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ProjectTab {
+    #[default]
+    Tasks,
+    History,
+}
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize, Default)]
@@ -30,6 +84,80 @@ pub struct TemplateApp {
     selected_project: Option<Rc<RefCell<Project>>>,
     #[serde(skip)] 
     projects: Vec<Rc<RefCell<Project>>>,
+    #[serde(skip)]
+    task_lists: Vec<TaskList>,
+    #[serde(skip)]
+    task_list_name: String,
+    #[serde(skip)]
+    selected_task_list: Option<i32>,
+    #[serde(skip)]
+    edit_task_list: Option<i32>,
+    #[serde(skip)]
+    edit_task_list_name: String,
+    #[serde(skip)]
+    project_drag: DragList,
+    // drag state of the tasks per task list, `None` holds the unsectioned tasks
+    #[serde(skip)]
+    task_drag: HashMap<Option<i32>, DragList>,
+    #[serde(skip)]
+    selected_task: Option<i32>,
+    #[serde(skip)]
+    editing_notes: bool,
+    #[serde(skip)]
+    notes_draft: String,
+    // checklist items by task id
+    #[serde(skip)]
+    checklists: HashMap<i32, Vec<ChecklistItem>>,
+    #[serde(skip)]
+    checklist_item_title: String,
+    #[serde(skip)]
+    labels: Vec<Label>,
+    #[serde(skip)]
+    task_labels: Vec<TaskLabel>,
+    #[serde(skip)]
+    label_name: String,
+    #[serde(skip)]
+    label_color: [u8; 3],
+    // only tasks with this label are shown
+    #[serde(skip)]
+    label_filter: Option<i32>,
+    #[serde(skip)]
+    search_query: String,
+    #[serde(skip)]
+    search_hits: Vec<SearchHit>,
+    #[serde(skip)]
+    undo: Option<Undo>,
+    #[serde(skip)]
+    errors: Vec<ErrorToast>,
+    #[serde(skip)]
+    project_tab: ProjectTab,
+    // history of the selected project, newest first
+    #[serde(skip)]
+    activity: Vec<Activity>,
+    #[serde(skip)]
+    activity_cursor: Option<Cursor>,
+    // where the next page of projects and of the selected project's tasks start
+    #[serde(skip)]
+    projects_cursor: Option<Cursor>,
+    #[serde(skip)]
+    tasks_cursor: Option<Cursor>,
+    // edit the server turned down, shown until the user resolves it
+    #[serde(skip)]
+    conflict: Option<Conflict>,
+    #[serde(skip)]
+    user: Option<AppUser>,
+    // shows the sign-in screen until the server knows us again
+    #[serde(skip)]
+    signed_out: bool,
+    #[serde(skip)]
+    token: String,
+    // tasks of the projects opened so far, shown until the server sends them anew
+    #[serde(skip)]
+    task_cache: HashMap<i32, Vec<Task>>,
+    #[serde(skip)]
+    online: bool,
+    #[serde(skip)]
+    next_reconnect: f64,
 }
 
 async fn fetch(url: &str) -> String {
@@ -39,15 +167,26 @@ async fn fetch(url: &str) -> String {
 }
 
 impl TemplateApp {
-    pub fn new(cc: &eframe::CreationContext<'_>, server_url: String) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, server: Client) -> Self {
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
         // if let Some(storage) = cc.storage {
         //     return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
         // }
+        let cache: Cache = cc.storage.and_then(|s| eframe::get_value(s, CACHE_KEY)).unwrap_or_default();
+        let outbox: Outbox = cc.storage.and_then(|s| eframe::get_value(s, OUTBOX_KEY)).unwrap_or_default();
         let (sender, receiver) = std::sync::mpsc::channel::<Update>();
-        let action = Action { sender: Some(sender), server_url: server_url.into() };
-        action.get_projects();
+        crate::api::events::listen(&server, sender.clone(), cc.egui_ctx.clone());
+        let action = Action {
+            sender: Some(sender),
+            server,
+            ctx: cc.egui_ctx.clone(),
+            outbox: Arc::new(Mutex::new(outbox)),
+            loading: Arc::new(Mutex::new(HashMap::new())),
+        };
+        action.get_user();
+        action.get_projects(None);
+        action.sync();
         TemplateApp { 
             action: Some(action),
             promise: None,
@@ -57,65 +196,482 @@ impl TemplateApp {
             edit_project: None,
             edit_project_name: String::new(),
             receiver: Some(receiver),
-            projects: Vec::new(),
-            project_name: String::new()
+            projects: cache.projects.into_iter().map(|p| Rc::new(RefCell::new(p))).collect(),
+            project_name: String::new(),
+            task_lists: Vec::new(),
+            task_list_name: String::new(),
+            selected_task_list: None,
+            edit_task_list: None,
+            edit_task_list_name: String::new(),
+            project_drag: DragList::default(),
+            task_drag: HashMap::new(),
+            selected_task: None,
+            editing_notes: false,
+            notes_draft: String::new(),
+            checklists: HashMap::new(),
+            checklist_item_title: String::new(),
+            labels: Vec::new(),
+            task_labels: Vec::new(),
+            label_name: String::new(),
+            label_color: [50, 100, 150],
+            label_filter: None,
+            search_query: String::new(),
+            search_hits: Vec::new(),
+            undo: None,
+            errors: Vec::new(),
+            project_tab: ProjectTab::Tasks,
+            activity: Vec::new(),
+            activity_cursor: None,
+            projects_cursor: None,
+            tasks_cursor: None,
+            conflict: None,
+            user: None,
+            signed_out: false,
+            token: String::new(),
+            task_cache: cache.tasks,
+            online: true,
+            next_reconnect: 0.0,
         }
     }
     fn action(&self) -> &Action {
         self.action.as_ref().unwrap()
     }
+
+    /// Asks the user to sign in, with the backend's login page on the web
+    /// and with an api token natively.
+    fn sign_in(&mut self, ctx: &egui::Context) {
+        let takes_token = self.action().server.transport.takes_token();
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(80.0);
+                ui.heading("Task Notes");
+                ui.label("Sign in to see your projects.");
+                ui.add_space(8.0);
+                let mut submit = false;
+                if takes_token {
+                    let token = ui.add(
+                        egui::TextEdit::singleline(&mut self.token)
+                            .password(true)
+                            .hint_text("Api token"),
+                    );
+                    submit = token.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                }
+                let ready = !takes_token || !self.token.trim().is_empty();
+                if ui.add_enabled(ready, egui::Button::new("Sign in")).clicked() || (submit && ready) {
+                    self.action().sign_in(&self.token);
+                    self.token.clear();
+                }
+            });
+        });
+    }
+
+    /// Keeps the tasks of the selected project for when it is opened offline.
+    fn stash_tasks(&mut self) {
+        if let Some(ref p) = self.selected_project {
+            self.task_cache.insert(p.borrow().id, self.tasks.clone());
+        }
+    }
+
+    /// Shows the projects the server sent, projects created offline stay until
+    /// the server has them.
+    fn replace_projects(&mut self, projects: Vec<Project>) {
+        let local: Vec<_> = self.projects.drain(..).filter(|p| p.borrow().id < 0).collect();
+        self.projects = projects.into_iter().map(|p| Rc::new(RefCell::new(p))).collect();
+        self.projects.extend(local);
+    }
+
+    /// Like `replace_projects` for the tasks of the selected project.
+    fn replace_tasks(&mut self, tasks: Vec<Task>) {
+        let local: Vec<Task> = self.tasks.drain(..).filter(|t| t.id < 0).collect();
+        self.tasks = tasks;
+        self.tasks.extend(local);
+    }
+
+    /// Shows a change before the server confirmed it.
+    fn show_pending(&mut self, mutation: Mutation) {
+        let selected = self.selected_project.as_ref().map(|p| p.borrow().id);
+        match mutation {
+            Mutation::CreateProject { local_id, project } => {
+                self.projects.push(Rc::new(RefCell::new(sync::local_project(local_id, &project))));
+            }
+            Mutation::EditProject(changes) => {
+                if let Some(p) = self.projects.iter().find(|p| p.borrow().id == changes.id) {
+                    changes.patch(&mut p.borrow_mut());
+                }
+            }
+            Mutation::DeleteProject(id) => {
+                if selected == Some(id) {
+                    self.selected_project = None;
+                    self.task_lists.clear();
+                    self.selected_task_list = None;
+                }
+                self.projects.retain(|p| p.borrow().id != id);
+            }
+            Mutation::CreateTask { local_id, task } => {
+                if selected == Some(task.project_id) {
+                    self.tasks.push(sync::local_task(local_id, &task));
+                }
+            }
+            Mutation::EditTask(changes) => {
+                if let Some(t) = self.tasks.iter_mut().find(|t| t.id == changes.id) {
+                    changes.patch(t);
+                }
+            }
+            Mutation::DeleteTask(id) => self.tasks.retain(|t| t.id != id),
+            // the server decides the positions, the task moves once it answered
+            Mutation::MoveTask(_) => {}
+        }
+    }
+
+    /// Shows the project and loads its task lists, tasks, checklists and labels.
+    fn select_project(&mut self, p: Rc<RefCell<Project>>) {
+        let pid = p.borrow().id;
+        self.stash_tasks();
+        self.tasks = self.task_cache.get(&pid).cloned().unwrap_or_default();
+        let action = self.action();
+        action.get_tasks(pid, None);
+        action.get_task_lists(pid);
+        action.get_checklist_items(pid);
+        action.get_labels(pid);
+        action.get_task_labels(pid);
+        if self.project_tab == ProjectTab::History {
+            action.get_activity(pid, None);
+        }
+        self.selected_project = Some(p);
+        self.selected_task_list = None;
+        self.label_filter = None;
+        self.activity.clear();
+        self.activity_cursor = None;
+        self.tasks_cursor = None;
+    }
+
+    /// Jumps to the project of a search hit and selects the hit within it.
+    fn open_search_hit(&mut self, hit: &SearchHit) {
+        let project = self.projects.iter().find(|p| p.borrow().id == hit.project_id).cloned();
+        if let Some(p) = project {
+            self.select_project(p);
+        }
+        match hit.kind {
+            SearchKind::Project => {}
+            SearchKind::TaskList => self.selected_task_list = Some(hit.id),
+            SearchKind::Task => {
+                self.selected_task = Some(hit.id);
+                self.editing_notes = false;
+            }
+        }
+        self.search_query.clear();
+        self.search_hits.clear();
+    }
 }
 
 impl eframe::App for TemplateApp {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
+        self.stash_tasks();
+        let cache = Cache {
+            projects: self.projects.iter().map(|p| p.borrow().clone()).collect(),
+            tasks: self.task_cache.clone(),
+        };
+        eframe::set_value(storage, CACHE_KEY, &cache);
+        eframe::set_value(storage, OUTBOX_KEY, &*self.action().outbox.lock().unwrap());
     }
 
     
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if let Some(ref mut r) = self.receiver {
-            if let Ok(m) = r.try_recv() {
-                match m {
-                    Update::ProjectList(project_list) => {
-                        self.projects = project_list.into_iter().map(|p| Rc::new(RefCell::new(p))).collect();
+        let now = ctx.input(|i| i.time);
+        // taken one at a time, the handlers need all of `self`
+        while let Some(m) = self.receiver.as_ref().and_then(|r| r.try_recv().ok()) {
+            match m {
+                Update::ProjectList(project_list) => {
+                    self.replace_projects(project_list);
+                    self.projects_cursor = None;
+                }
+                Update::TaskList(tasks) => {
+                    self.replace_tasks(tasks);
+                    self.tasks_cursor = None;
+                }
+                Update::Projects(cursor, page) => {
+                    if cursor.is_none() {
+                        self.replace_projects(page.items);
+                    } else {
+                        // after the loaded ones, projects created offline stay last
+                        let new: Vec<_> = page.items.into_iter()
+                            .filter(|p| !self.projects.iter().any(|q| q.borrow().id == p.id))
+                            .map(|p| Rc::new(RefCell::new(p)))
+                            .collect();
+                        let at = self.projects.iter().position(|p| p.borrow().id < 0).unwrap_or(self.projects.len());
+                        self.projects.splice(at..at, new);
                     }
-                    Update::TaskList(tasks) => {
-                        self.tasks = tasks;
+                    self.projects_cursor = page.next_cursor;
+                }
+                Update::Tasks(project_id, cursor, page) => {
+                    if self.selected_project.as_ref().map(|p| p.borrow().id) == Some(project_id) {
+                        if cursor.is_none() {
+                            self.replace_tasks(page.items);
+                        } else {
+                            let new: Vec<Task> = page.items.into_iter()
+                                .filter(|t| !self.tasks.iter().any(|u| u.id == t.id))
+                                .collect();
+                            let at = self.tasks.iter().position(|t| t.id < 0).unwrap_or(self.tasks.len());
+                            self.tasks.splice(at..at, new);
+                        }
+                        self.tasks_cursor = page.next_cursor;
                     }
-                    Update::ProjectCreated(project) => {
+                }
+                Update::ProjectCreated(project) => {
+                    // the change event may have been faster
+                    if !self.projects.iter().any(|p| p.borrow().id == project.id) {
                         self.projects.push(Rc::new(RefCell::new(project)));
                     }
-                    Update::TaskDeleted(task) => {
-                        let pos = self.tasks.iter().position(|t| t.id == task.id);
-                        if let Some(pos) = pos {
-                            self.tasks.remove(pos);
+                }
+                Update::TaskDeleted(task) => {
+                    let pos = self.tasks.iter().position(|t| t.id == task.id);
+                    if let Some(pos) = pos {
+                        self.tasks.remove(pos);
+                    }
+                    self.undo = Some(Undo {
+                        item: TrashItem::Task(task.id),
+                        message: format!("Task '{}' moved to the trash", task.title),
+                        until: now + UNDO_SECONDS,
+                    });
+                }
+                Update::ProjectChanged(project) => {
+                    if let Some(changed_project) = self.projects.iter().find(|p| p.borrow().id == project.id) {
+                        *changed_project.borrow_mut() = project;
+                    }
+                }
+                Update::TaskCreated(task) => {
+                    if !self.tasks.iter().any(|t| t.id == task.id) {
+                        self.tasks.push(task);
+                    }
+                }
+                Update::TaskChanged(task) => {
+                    if let Some(changed) = self.tasks.iter_mut().find(|t| t.id == task.id) {
+                        *changed = task;
+                    }
+                }
+                Update::ProjectDeleted(project) => {
+                    if let Some(ref selected) = self.selected_project {
+                        if selected.borrow().id == project.id {
+                            self.selected_project = None;
+                            self.task_lists.clear();
+                            self.selected_task_list = None;
                         }
                     }
-                    Update::ProjectChanged(project) => {
-                        if let Some(changed_project) = self.projects.iter().find(|p| p.borrow().id == project.id) {
-                            project.patch(&mut changed_project.borrow_mut());
+                    let pos = self.projects.iter().position(|p| p.borrow().id == project.id);
+                    if let Some(pos) = pos {
+                        self.projects.remove(pos);
+                    }
+                    self.undo = Some(Undo {
+                        item: TrashItem::Project(project.id),
+                        message: format!("Project '{}' moved to the trash", project.title),
+                        until: now + UNDO_SECONDS,
+                    });
+                }
+                Update::Restored(trash) => {
+                    for project in trash.projects {
+                        self.projects.push(Rc::new(RefCell::new(project)));
+                    }
+                    self.projects.sort_by_key(|p| (p.borrow().priority, p.borrow().id));
+                    let selected = self.selected_project.as_ref().map(|p| p.borrow().id);
+                    for task in trash.tasks {
+                        if Some(task.project_id) == selected {
+                            self.tasks.push(task);
                         }
                     }
-                    Update::TaskCreated(task) => {
-                        self.tasks.push(task);
+                    self.tasks.sort_by_key(|t| (t.position, t.id));
+                }
+                Update::TaskLists(task_lists) => {
+                    self.task_lists = task_lists;
+                }
+                Update::TaskListCreated(task_list) => {
+                    self.task_lists.push(task_list);
+                }
+                Update::TaskListChanged(task_list) => {
+                    if let Some(changed) = self.task_lists.iter_mut().find(|l| l.id == task_list.id) {
+                        *changed = task_list;
+                    }
+                    self.task_lists.sort_by_key(|l| (l.position, l.id));
+                }
+                Update::ChecklistItems(items) => {
+                    self.checklists.clear();
+                    for item in items {
+                        self.checklists.entry(item.task_id).or_default().push(item);
+                    }
+                }
+                Update::Checklist(checklist) => {
+                    self.checklists.insert(checklist.task.id, checklist.items);
+                    if let Some(changed) = self.tasks.iter_mut().find(|t| t.id == checklist.task.id) {
+                        *changed = checklist.task;
+                    }
+                }
+                Update::Labels(labels) => {
+                    self.labels = labels;
+                }
+                Update::LabelCreated(label) => {
+                    self.labels.push(label);
+                    self.labels.sort_by(|a, b| a.name.cmp(&b.name));
+                }
+                Update::LabelDeleted(label) => {
+                    self.labels.retain(|l| l.id != label.id);
+                    self.task_labels.retain(|tl| tl.label_id != label.id);
+                    if self.label_filter == Some(label.id) {
+                        self.label_filter = None;
+                    }
+                }
+                Update::TaskLabels(task_labels) => {
+                    self.task_labels = task_labels;
+                }
+                Update::LabelAttached(task_label) => {
+                    if !self.task_labels.contains(&task_label) {
+                        self.task_labels.push(task_label);
+                    }
+                }
+                Update::LabelDetached(task_label) => {
+                    self.task_labels.retain(|tl| *tl != task_label);
+                }
+                Update::SearchResults(q, hits) => {
+                    if q == self.search_query.trim() {
+                        self.search_hits = hits;
                     }
-                    Update::ProjectDeleted(project) => {
-                        if let Some(ref selected) = self.selected_project {
-                            if selected.borrow().id == project.id {
+                }
+                Update::Activity(project_id, cursor, page) => {
+                    if self.selected_project.as_ref().map(|p| p.borrow().id) == Some(project_id) {
+                        if cursor.is_none() {
+                            self.activity.clear();
+                        }
+                        self.activity.extend(page.items);
+                        self.activity_cursor = page.next_cursor;
+                    }
+                }
+                Update::Event(event) => {
+                    let selected = self.selected_project.as_ref().map(|p| p.borrow().id);
+                    match event {
+                        ChangeEvent::ProjectChanged(project) => {
+                            // the event carries the shared priority, the list keeps the user's own order
+                            match self.projects.iter().find(|p| p.borrow().id == project.id) {
+                                Some(changed) => {
+                                    let priority = changed.borrow().priority;
+                                    *changed.borrow_mut() = Project { priority, ..project };
+                                }
+                                None => self.projects.push(Rc::new(RefCell::new(project))),
+                            }
+                        }
+                        ChangeEvent::ProjectDeleted(project) => {
+                            if selected == Some(project.id) {
                                 self.selected_project = None;
+                                self.task_lists.clear();
+                                self.selected_task_list = None;
+                            }
+                            self.projects.retain(|p| p.borrow().id != project.id);
+                        }
+                        ChangeEvent::TaskChanged(task) if selected == Some(task.project_id) => {
+                            match self.tasks.iter_mut().find(|t| t.id == task.id) {
+                                Some(changed) => *changed = task,
+                                None => self.tasks.push(task),
                             }
+                            self.tasks.sort_by_key(|t| (t.position, t.id));
+                        }
+                        ChangeEvent::TaskDeleted(task) => {
+                            self.tasks.retain(|t| t.id != task.id);
+                        }
+                        ChangeEvent::TasksMoved(tasks) if tasks.first().map(|t| t.project_id) == selected => {
+                            self.tasks = tasks;
                         }
-                        let pos = self.projects.iter().position(|p| p.borrow().id == project.id);
-                        if let Some(pos) = pos {
-                            self.projects.remove(pos);
+                        ChangeEvent::TaskChanged(_) | ChangeEvent::TasksMoved(_) => {}
+                    }
+                }
+                Update::Conflict(conflict) => {
+                    self.conflict = Some(conflict);
+                }
+                Update::TaskListDeleted(task_list) => {
+                    self.task_lists.retain(|l| l.id != task_list.id);
+                    // the server moved the tasks of the list to the trash
+                    self.tasks.retain(|t| t.task_list_id != Some(task_list.id));
+                    if self.selected_task_list == Some(task_list.id) {
+                        self.selected_task_list = None;
+                    }
+                }
+                Update::Pending(mutation) => self.show_pending(mutation),
+                Update::ProjectSynced(local_id, project) => {
+                    // the change event may have been faster
+                    self.projects.retain(|p| p.borrow().id != project.id);
+                    for t in self.tasks.iter_mut().filter(|t| t.project_id == local_id) {
+                        t.project_id = project.id;
+                    }
+                    if let Some(tasks) = self.task_cache.remove(&local_id) {
+                        self.task_cache.insert(project.id, tasks);
+                    }
+                    // replaced in place so a selection of it carries over
+                    if let Some(p) = self.projects.iter().find(|p| p.borrow().id == local_id) {
+                        *p.borrow_mut() = project;
+                    }
+                }
+                Update::TaskSynced(local_id, task) => {
+                    self.tasks.retain(|t| t.id != task.id);
+                    if self.selected_task == Some(local_id) {
+                        self.selected_task = Some(task.id);
+                    }
+                    if let Some(t) = self.tasks.iter_mut().find(|t| t.id == local_id) {
+                        *t = task;
+                    }
+                }
+                Update::User(user) => {
+                    self.user = Some(user);
+                    self.signed_out = false;
+                }
+                Update::Error(failure) => {
+                    let offline = matches!(failure.error, ApiError::Network(_));
+                    if failure.retry.is_some() && matches!(failure.resource, Resource::Projects | Resource::Tasks) {
+                        // the change already shows, fetch what the server kept instead
+                        self.action().get_projects(None);
+                        if let Some(p) = self.selected_project.clone() {
+                            self.select_project(p);
                         }
                     }
+                    // the offline indicator tells about reads that did not get through
+                    if failure.retry.is_some() || !offline {
+                        let until = failure.retry.is_none().then_some(now + ERROR_SECONDS);
+                        self.errors.push(ErrorToast { failure, until });
+                    }
+                }
+                Update::SignedOut => {
+                    self.user = None;
+                    self.errors.clear();
+                    self.signed_out = true;
+                    self.projects.clear();
+                    self.tasks.clear();
+                    self.task_cache.clear();
+                    self.selected_project = None;
+                    self.selected_task = None;
                 }
             }
         }
         
+        let online = self.action().server.is_online();
+        if online && !self.online {
+            // catch up with what changed while we were away
+            self.action().get_projects(None);
+            if let Some(p) = self.selected_project.clone() {
+                self.select_project(p);
+            }
+        }
+        self.online = online;
+        if !online {
+            if now >= self.next_reconnect {
+                self.action().reconnect();
+                self.next_reconnect = now + RECONNECT_SECONDS;
+            }
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(RECONNECT_SECONDS));
+        }
+
+        if self.signed_out {
+            self.sign_in(ctx);
+            return;
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
 
@@ -129,9 +685,125 @@ impl eframe::App for TemplateApp {
                     });
                     ui.add_space(16.0);
                 }
+                let search = ui.add(
+                    egui::TextEdit::singleline(&mut self.search_query)
+                        .hint_text("🔍 Search projects and tasks")
+                        .desired_width(240.0),
+                );
+                if search.changed() {
+                    let q = self.search_query.trim();
+                    if q.is_empty() {
+                        self.search_hits.clear();
+                    } else {
+                        self.action().search(q);
+                    }
+                }
+                if !self.search_query.is_empty() && ui.small_button("✖").clicked() {
+                    self.search_query.clear();
+                    self.search_hits.clear();
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let action = self.action.as_ref().unwrap();
+                    if let Some(ref user) = self.user {
+                        if ui.button("Log out").clicked() {
+                            action.sign_out();
+                        }
+                        ui.label(&user.email);
+                        ui.separator();
+                    }
+                    let pending = action.outbox.lock().unwrap().len();
+                    if pending > 0 {
+                        ui.label(format!("{} to sync", pending))
+                            .on_hover_text("Changes the server has not confirmed yet");
+                    }
+                    if self.online {
+                        ui.colored_label(egui::Color32::from_rgb(90, 170, 90), "● online");
+                    } else {
+                        ui.colored_label(ui.visuals().warn_fg_color, "○ offline")
+                            .on_hover_text("Changes are kept and sent once the server is back");
+                    }
+                });
             });
         });
 
+        if !self.search_query.trim().is_empty() {
+            let mut opened = None;
+            egui::Window::new("Search results")
+                .anchor(egui::Align2::LEFT_TOP, [8.0, 32.0])
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    if self.action().is_loading(Resource::Search) {
+                        ui.spinner();
+                    } else if self.search_hits.is_empty() {
+                        ui.weak("No matches");
+                    }
+                    for hit in &self.search_hits {
+                        if search_hit_row(ui, hit).clicked() {
+                            opened = Some(hit.clone());
+                        }
+                    }
+                });
+            if let Some(hit) = opened {
+                self.open_search_hit(&hit);
+            }
+        }
+
+        if let Some(conflict) = self.conflict.take() {
+            let mut resolved = false;
+            let (what, mine, theirs) = match conflict {
+                Conflict::Project { ref mine, ref theirs } => {
+                    (format!("Project '{}'", theirs.title), serde_json::to_value(mine), serde_json::to_value(theirs))
+                }
+                Conflict::Task { ref mine, ref theirs } => {
+                    (format!("Task '{}'", theirs.title), serde_json::to_value(mine), serde_json::to_value(theirs))
+                }
+            };
+            egui::Window::new("Edit conflict")
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("{} was changed by someone else while you edited it.", what));
+                    if let (Ok(mine), Ok(theirs)) = (mine, theirs) {
+                        conflict_rows(ui, &mine, &theirs);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Keep mine").on_hover_text("Apply your changes over theirs").clicked() {
+                            match conflict {
+                                Conflict::Project { ref mine, ref theirs } => self.action().edit_project(PatchProject {
+                                    version: Some(theirs.version),
+                                    ..mine.clone()
+                                }),
+                                Conflict::Task { ref mine, ref theirs } => self.action().edit_task(PatchTask {
+                                    version: Some(theirs.version),
+                                    ..mine.clone()
+                                }),
+                            }
+                            resolved = true;
+                        }
+                        if ui.button("Use theirs").on_hover_text("Drop your changes").clicked() {
+                            match conflict {
+                                Conflict::Project { ref theirs, .. } => {
+                                    if let Some(p) = self.projects.iter().find(|p| p.borrow().id == theirs.id) {
+                                        *p.borrow_mut() = theirs.clone();
+                                    }
+                                }
+                                Conflict::Task { ref theirs, .. } => {
+                                    if let Some(t) = self.tasks.iter_mut().find(|t| t.id == theirs.id) {
+                                        *t = theirs.clone();
+                                    }
+                                }
+                            }
+                            resolved = true;
+                        }
+                    });
+                });
+            if !resolved {
+                self.conflict = Some(conflict);
+            }
+        }
+
         egui::SidePanel::new(egui::panel::Side::Left, "left").show(ctx, |ui| {
             ui.label("Enter new project name:");
             ui.text_edit_singleline(&mut self.project_name);
@@ -145,14 +817,20 @@ impl eframe::App for TemplateApp {
                 }
             }
             ui.separator();
-            ui.label("Project list:");
+            ui.horizontal(|ui| {
+                ui.label("Project list:");
+                if self.action().is_loading(Resource::Projects) {
+                    ui.spinner();
+                }
+            });
             let mut id = None;
             if let Some(ref edit_project) = self.edit_project {
                 id = Some(edit_project.borrow().id);
             }
             let action: Action = self.action().clone();
-            for p in &self.projects {
-                ui.horizontal(|ui| {
+            let mut clicked = None;
+            for (i, p) in self.projects.iter().enumerate() {
+                self.project_drag.row(ui, i, |ui| {
                     let x_button = egui::Button::new("X")
                         .fill(egui::Color32::from_rgb(90, 20, 20));
                     if ui.add(x_button).clicked() {
@@ -178,7 +856,8 @@ impl eframe::App for TemplateApp {
                             if !p.borrow().title.eq(&self.edit_project_name) {
                                 let patched = PatchProject {
                                     id: p.borrow().id,
-                                    title: self.edit_project_name.clone()
+                                    title: Some(self.edit_project_name.clone()),
+                                    version: Some(p.borrow().version)
                                 };
                                 action.edit_project(patched);
                             }
@@ -192,44 +871,349 @@ impl eframe::App for TemplateApp {
                         }
                             
                         if ui.add(project_button).clicked() {
-                            self.selected_project = Some(p.clone());
-                            action.get_tasks(p.borrow().id);
+                            clicked = Some(p.clone());
                         };
                     }
                 });
             }
+            if let Some(cursor) = self.projects_cursor {
+                if ui.button("Load more projects").clicked() {
+                    action.get_projects(Some(cursor));
+                }
+            }
+            if let Some(p) = clicked {
+                self.select_project(p);
+            }
+            if let Some((from, to)) = self.project_drag.finish(ui) {
+                let moved = self.projects.remove(from);
+                self.projects.insert(to, moved);
+                action.reorder_projects(ProjectOrder {
+                    project_ids: self.projects.iter().map(|p| p.borrow().id).collect()
+                });
+            }
         });
 
+        let selected_task = self.selected_task
+            .and_then(|id| self.tasks.iter().find(|t| t.id == id))
+            .cloned();
+        if let Some(t) = selected_task {
+            let action: Action = self.action().clone();
+            egui::SidePanel::right("task_detail").default_width(320.0).show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").on_hover_text("Close").clicked() {
+                        self.selected_task = None;
+                    }
+                    ui.heading(&t.title);
+                });
+                ui.separator();
+                ui.strong("Checklist");
+                let items = self.checklists.get(&t.id).cloned().unwrap_or_default();
+                let mut order: Vec<i32> = items.iter().map(|item| item.id).collect();
+                let mut reordered = false;
+                for (ii, item) in items.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("✖").on_hover_text("Remove the item").clicked() {
+                            action.delete_checklist_item(item.id);
+                        }
+                        if ui.add_enabled(ii > 0, egui::Button::new("⬆").small()).clicked() {
+                            order.swap(ii, ii - 1);
+                            reordered = true;
+                        }
+                        if ui.add_enabled(ii + 1 < items.len(), egui::Button::new("⬇").small()).clicked() {
+                            order.swap(ii, ii + 1);
+                            reordered = true;
+                        }
+                        let mut done = item.done;
+                        if ui.checkbox(&mut done, &item.title).changed() {
+                            action.edit_checklist_item(PatchChecklistItem {
+                                id: item.id,
+                                done: Some(done),
+                                ..Default::default()
+                            });
+                        }
+                    });
+                }
+                if reordered {
+                    action.reorder_checklist(ChecklistOrder { task_id: t.id, item_ids: order });
+                }
+                ui.horizontal(|ui| {
+                    let response = ui.text_edit_singleline(&mut self.checklist_item_title);
+                    let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if (ui.button("Add item").clicked() || submitted) && !self.checklist_item_title.trim().is_empty() {
+                        action.create_checklist_item(t.id, self.checklist_item_title.trim());
+                        self.checklist_item_title.clear();
+                    }
+                });
+                ui.separator();
+                if self.editing_notes {
+                    let too_long = self.notes_draft.len() > MAX_NOTES_LEN;
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(!too_long, egui::Button::new("Save")).clicked() {
+                            action.edit_task(PatchTask {
+                                id: t.id,
+                                version: Some(t.version),
+                                notes: Some(self.notes_draft.clone()),
+                                ..Default::default()
+                            });
+                            self.editing_notes = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.editing_notes = false;
+                        }
+                        let size = egui::RichText::new(format!("{} / {} bytes", self.notes_draft.len(), MAX_NOTES_LEN));
+                        ui.label(if too_long { size.color(ui.visuals().error_fg_color) } else { size.weak() });
+                    });
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.add(egui::TextEdit::multiline(&mut self.notes_draft)
+                            .code_editor()
+                            .desired_rows(16)
+                            .desired_width(f32::INFINITY));
+                    });
+                } else {
+                    if ui.button("Edit notes").clicked() {
+                        self.notes_draft = t.notes.clone();
+                        self.editing_notes = true;
+                    }
+                    ui.separator();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        if t.notes.trim().is_empty() {
+                            ui.weak("No notes yet.");
+                        } else {
+                            crate::markdown::show(ui, &t.notes);
+                        }
+                    });
+                }
+            });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
+            let action: Action = self.action().clone();
             if let Some(ref selected_project) = self.selected_project {
                 ui.heading(&format!("Project:    '{}'", selected_project.borrow().title));
                 ui.separator();
-                ui.label("Enter task description:");
-                ui.text_edit_multiline(&mut self.task_text);
-                let mut create_task_button = egui::Button::new(
-                    egui::RichText::new("Create New Task")
-                        .color(egui::Color32::from_rgb(255, 255, 255))
-                    ).fill(egui::Color32::from_rgb(20, 150, 20));
-                if ui.add(create_task_button).clicked() && !self.task_text.trim().is_empty() {
-                    self.action().create_task(&self.task_text, selected_project.borrow().id);
-                    self.task_text.clear();
+                let pid = selected_project.borrow().id;
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.project_tab, ProjectTab::Tasks, "Tasks");
+                    if ui.selectable_value(&mut self.project_tab, ProjectTab::History, "History").clicked() {
+                        action.get_activity(pid, None);
+                    }
+                });
+                ui.separator();
+                if self.project_tab == ProjectTab::Tasks {
+                    ui.label("Enter task title:");
+                    ui.text_edit_singleline(&mut self.task_text);
+                    ui.horizontal(|ui| {
+                        ui.label("Section:");
+                        let selected_text = self.selected_task_list
+                            .and_then(|lid| self.task_lists.iter().find(|l| l.id == lid))
+                            .map(|l| l.title.clone())
+                            .unwrap_or_else(|| "No section".to_owned());
+                        egui::ComboBox::from_id_source("task_list_select")
+                            .selected_text(selected_text)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.selected_task_list, None, "No section");
+                                for l in &self.task_lists {
+                                    ui.selectable_value(&mut self.selected_task_list, Some(l.id), &l.title);
+                                }
+                            });
+                    });
+                    let create_task_button = egui::Button::new(
+                        egui::RichText::new("Create New Task")
+                            .color(egui::Color32::from_rgb(255, 255, 255))
+                        ).fill(egui::Color32::from_rgb(20, 150, 20));
+                    if ui.add(create_task_button).clicked() && !self.task_text.trim().is_empty() {
+                        action.create_task(&self.task_text, selected_project.borrow().id, self.selected_task_list);
+                        self.task_text.clear();
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Enter new section name:");
+                        ui.text_edit_singleline(&mut self.task_list_name);
+                        if ui.button("Create Section").clicked() {
+                            let name = self.task_list_name.trim();
+                            if !name.is_empty() {
+                                action.create_task_list(name, selected_project.borrow().id);
+                                self.task_list_name.clear();
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Enter new label name:");
+                        ui.text_edit_singleline(&mut self.label_name);
+                        ui.color_edit_button_srgb(&mut self.label_color);
+                        if ui.button("Create Label").clicked() {
+                            let name = self.label_name.trim();
+                            if !name.is_empty() {
+                                let [r, g, b] = self.label_color;
+                                action.create_label(selected_project.borrow().id, name, format!("#{:02x}{:02x}{:02x}", r, g, b));
+                                self.label_name.clear();
+                            }
+                        }
+                    });
+                    if !self.labels.is_empty() {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label("Filter by label:");
+                            for l in &self.labels {
+                                let active = self.label_filter == Some(l.id);
+                                let response = ui.add(label_chip(l, active))
+                                    .on_hover_text("Click to filter, right click for more");
+                                if response.clicked() {
+                                    self.label_filter = if active { None } else { Some(l.id) };
+                                }
+                                response.context_menu(|ui| {
+                                    if ui.button("Delete label").clicked() {
+                                        action.delete_label(l.id);
+                                        ui.close_menu();
+                                    }
+                                });
+                            }
+                        });
+                    }
                 }
             }
             
-            ui.separator();
-            ui.label("Tasks:");
-            ui.separator();
-            // The central panel the region left after adding TopPanel's and SidePanel's
-            for (i, t) in self.tasks.iter().enumerate() {
+            if self.selected_project.is_some() && self.project_tab == ProjectTab::History {
+                if action.is_loading(Resource::Activity) {
+                    ui.spinner();
+                }
+                egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                    if self.activity.is_empty() {
+                        ui.weak("Nothing has happened yet.");
+                    }
+                    for a in &self.activity {
+                        activity_row(ui, a);
+                    }
+                    if let Some(cursor) = self.activity_cursor {
+                        if ui.button("Load older").clicked() {
+                            if let Some(ref p) = self.selected_project {
+                                action.get_activity(p.borrow().id, Some(cursor));
+                            }
+                        }
+                    }
+                });
+            } else {
+                ui.separator();
                 ui.horizontal(|ui| {
-                    let delete_task_button = egui::Button::new("X")
-                        .fill(egui::Color32::from_rgb(90, 20, 20));
-                    if ui.add(delete_task_button).clicked() {
-                        self.action().delete_task(t.id);
+                    ui.label("Tasks:");
+                    if [Resource::Tasks, Resource::TaskLists, Resource::Labels].iter().any(|&r| action.is_loading(r)) {
+                        ui.spinner();
                     }
-                    ui.label(&format!("{:<2}. ", i + 1));
-                    ui.label(&t.title);
                 });
+                ui.separator();
+                // The central panel the region left after adding TopPanel's and SidePanel's
+                let mut moved: Option<MoveTask> = None;
+                let mut clicked: Option<i32> = None;
+                let section: Vec<&Task> = self.tasks.iter()
+                    .filter(|t| t.task_list_id.is_none())
+                    .filter(|t| self.label_filter.map_or(true, |lid| has_label(&self.task_labels, t.id, lid)))
+                    .collect();
+                let drag = self.task_drag.entry(None).or_default();
+                for (i, t) in section.iter().enumerate() {
+                    drag.row(ui, i, |ui| {
+                        if task_row(ui, &action, i, t, self.selected_task == Some(t.id), &self.labels, &self.task_labels) {
+                            clicked = Some(t.id);
+                        }
+                    });
+                    checklist_rows(ui, &action, t.id, self.checklists.get(&t.id));
+                }
+                if let Some((from, to)) = drag.finish(ui) {
+                    moved = Some(dropped_task(&section, from, to));
+                }
+                let mut order: Vec<i32> = self.task_lists.iter().map(|l| l.id).collect();
+                let mut reordered = false;
+                let list_count = self.task_lists.len();
+                for (li, l) in self.task_lists.iter().enumerate() {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let x_button = egui::Button::new("X")
+                            .fill(egui::Color32::from_rgb(90, 20, 20));
+                        if ui.add(x_button).clicked() {
+                            action.delete_task_list(l.id);
+                        }
+                        if ui.add_enabled(li > 0, egui::Button::new("⬆")).clicked() {
+                            order.swap(li, li - 1);
+                            reordered = true;
+                        }
+                        if ui.add_enabled(li + 1 < list_count, egui::Button::new("⬇")).clicked() {
+                            order.swap(li, li + 1);
+                            reordered = true;
+                        }
+                        if self.edit_task_list == Some(l.id) {
+                            let response = ui.text_edit_singleline(&mut self.edit_task_list_name);
+                            if ui.button("cancel").clicked() {
+                                self.edit_task_list = None;
+                            }
+                            if ui.button("ok").clicked() || response.lost_focus() && response.ctx.input(|r|{r.key_pressed(egui::Key::Enter)}) {
+                                self.edit_task_list = None;
+                                if !l.title.eq(&self.edit_task_list_name) {
+                                    action.edit_task_list(PatchTaskList {
+                                        id: l.id,
+                                        title: Some(self.edit_task_list_name.clone()),
+                                        position: None
+                                    });
+                                }
+                            }
+                        } else {
+                            let edit_button = egui::Button::new("Edit")
+                                .fill(egui::Color32::from_rgb(90, 90, 20));
+                            if ui.add(edit_button).clicked() {
+                                self.edit_task_list_name = l.title.clone();
+                                self.edit_task_list = Some(l.id);
+                            }
+                            ui.strong(&l.title);
+                        }
+                    });
+                    ui.indent(("task_list", l.id), |ui| {
+                        let section: Vec<&Task> = self.tasks.iter()
+                            .filter(|t| t.task_list_id == Some(l.id))
+                            .filter(|t| self.label_filter.map_or(true, |lid| has_label(&self.task_labels, t.id, lid)))
+                            .collect();
+                        let drag = self.task_drag.entry(Some(l.id)).or_default();
+                        for (i, t) in section.iter().enumerate() {
+                            drag.row(ui, i, |ui| {
+                                if task_row(ui, &action, i, t, self.selected_task == Some(t.id), &self.labels, &self.task_labels) {
+                                    clicked = Some(t.id);
+                                }
+                            });
+                            checklist_rows(ui, &action, t.id, self.checklists.get(&t.id));
+                        }
+                        if let Some((from, to)) = drag.finish(ui) {
+                            moved = Some(dropped_task(&section, from, to));
+                        }
+                    });
+                }
+                if let Some(cursor) = self.tasks_cursor {
+                    ui.separator();
+                    if ui.button("Load more tasks").clicked() {
+                        if let Some(ref p) = self.selected_project {
+                            action.get_tasks(p.borrow().id, Some(cursor));
+                        }
+                    }
+                }
+                if let Some(id) = clicked {
+                    self.selected_task = Some(id);
+                    self.editing_notes = false;
+                }
+                if let Some(m) = moved {
+                    // show the new order right away, the server answers with the renumbered tasks
+                    if let Some(from) = self.tasks.iter().position(|t| t.id == m.id) {
+                        let t = self.tasks.remove(from);
+                        let anchor = m.before.or(m.after);
+                        if let Some(at) = self.tasks.iter().position(|t| Some(t.id) == anchor) {
+                            let at = if m.after.is_some() { at + 1 } else { at };
+                            self.tasks.insert(at, t);
+                        }
+                    }
+                    action.move_task(m);
+                }
+                if reordered {
+                    if let Some(ref selected_project) = self.selected_project {
+                        action.reorder_task_lists(TaskListOrder {
+                            project_id: selected_project.borrow().id,
+                            task_list_ids: order
+                        });
+                    }
+                }
             }
 
             ui.separator();
@@ -239,6 +1223,303 @@ impl eframe::App for TemplateApp {
                 egui::warn_if_debug_build(ui);
             });
         });
+
+        self.errors.retain(|e| e.until.map_or(true, |until| now < until));
+        if !self.errors.is_empty() {
+            let mut dismissed = None;
+            egui::Area::new("error_toasts")
+                .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -64.0])
+                .show(ctx, |ui| {
+                    for (i, e) in self.errors.iter().enumerate() {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.colored_label(ui.visuals().error_fg_color, e.failure.message());
+                                if let Some(ref retry) = e.failure.retry {
+                                    if ui.button("Retry").clicked() {
+                                        retry(self.action());
+                                        dismissed = Some(i);
+                                    }
+                                }
+                                if ui.small_button("✖").clicked() {
+                                    dismissed = Some(i);
+                                }
+                            });
+                        });
+                    }
+                });
+            if let Some(i) = dismissed {
+                self.errors.remove(i);
+            }
+            if let Some(until) = self.errors.iter().filter_map(|e| e.until).reduce(f64::min) {
+                ctx.request_repaint_after(std::time::Duration::from_secs_f64(until - now));
+            }
+        }
+
+        if let Some(undo) = self.undo.take() {
+            if now < undo.until {
+                let mut keep = true;
+                egui::Area::new("undo_toast")
+                    .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -24.0])
+                    .show(ctx, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(&undo.message);
+                                if ui.button("Undo").clicked() {
+                                    self.action().restore(undo.item);
+                                    keep = false;
+                                }
+                                if ui.small_button("✖").clicked() {
+                                    keep = false;
+                                }
+                            });
+                        });
+                    });
+                if keep {
+                    ctx.request_repaint_after(std::time::Duration::from_secs_f64(undo.until - now));
+                    self.undo = Some(undo);
+                }
+            }
+        }
+    }
+}
+
+/// Lays out one task, returns whether its title was clicked.
+fn task_row(ui: &mut egui::Ui, action: &Action, i: usize, t: &Task, selected: bool, labels: &[Label], task_labels: &[TaskLabel]) -> bool {
+    let delete_task_button = egui::Button::new("X")
+        .fill(egui::Color32::from_rgb(90, 20, 20));
+    if ui.add(delete_task_button).clicked() {
+        action.delete_task(t.id);
+    }
+    ui.label(format!("{:<2}. ", i + 1));
+    let mut completed = t.completed;
+    if ui.checkbox(&mut completed, "").changed() {
+        action.edit_task(PatchTask {
+            id: t.id,
+            version: Some(t.version),
+            completed: Some(completed),
+            ..Default::default()
+        });
+    }
+    let overdue = !t.completed && t.due_at.is_some_and(|due_at| due_at < Utc::now());
+    let mut title = egui::RichText::new(&t.title);
+    if t.completed {
+        title = title.strikethrough().weak();
+    } else if overdue {
+        title = title.color(ui.visuals().error_fg_color);
+    }
+    let clicked = ui.selectable_label(selected, title).clicked();
+    if t.checklist_total > 0 {
+        ui.weak(format!("{}/{} done", t.checklist_done, t.checklist_total));
+    }
+    let (attached, detached): (Vec<&Label>, Vec<&Label>) = labels.iter()
+        .partition(|l| has_label(task_labels, t.id, l.id));
+    for l in attached {
+        if ui.add(label_chip(l, false)).on_hover_text("Click to remove").clicked() {
+            action.detach_label(t.id, l.id);
+        }
+    }
+    if !detached.is_empty() {
+        ui.menu_button("🏷", |ui| {
+            for l in detached {
+                if ui.add(label_chip(l, false)).clicked() {
+                    action.attach_label(t.id, l.id);
+                    ui.close_menu();
+                }
+            }
+        });
+    }
+    match t.due_at {
+        Some(due_at) => {
+            let mut due = due_at.with_timezone(&Local).date_naive();
+            let before = due;
+            ui.add(egui_extras::DatePickerButton::new(&mut due).id_source(&format!("due_{}", t.id)));
+            if due != before {
+                action.edit_task(PatchTask {
+                    id: t.id,
+                    version: Some(t.version),
+                    due_at: Some(end_of_day(due)),
+                    ..Default::default()
+                });
+            }
+            if ui.small_button("✖").on_hover_text("Remove the due date").clicked() {
+                action.edit_task(PatchTask {
+                    id: t.id,
+                    version: Some(t.version),
+                    due_at: Some(None),
+                    ..Default::default()
+                });
+            }
+        }
+        None => {
+            if ui.small_button("📅").on_hover_text("Set a due date").clicked() {
+                action.edit_task(PatchTask {
+                    id: t.id,
+                    version: Some(t.version),
+                    due_at: Some(end_of_day(Local::now().date_naive())),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+    clicked
+}
+
+fn has_label(task_labels: &[TaskLabel], task_id: i32, label_id: i32) -> bool {
+    task_labels.iter().any(|tl| tl.task_id == task_id && tl.label_id == label_id)
+}
+
+/// One clickable search result with the matching words in bold.
+fn search_hit_row(ui: &mut egui::Ui, hit: &SearchHit) -> egui::Response {
+    let icon = match hit.kind {
+        SearchKind::Project => "📁",
+        SearchKind::TaskList => "☰",
+        SearchKind::Task => "☐",
+    };
+    let mut job = egui::text::LayoutJob::default();
+    let style = ui.style();
+    let color = style.visuals.text_color();
+    let strong = style.visuals.strong_text_color();
+    let font = egui::TextStyle::Body.resolve(style);
+    job.append(&format!("{} ", icon), 0.0, egui::TextFormat::simple(font.clone(), color));
+    for (text, highlighted) in hit.snippet_parts() {
+        let mut format = egui::TextFormat::simple(font.clone(), if highlighted { strong } else { color });
+        if highlighted {
+            format.underline = egui::Stroke::new(1.0, strong);
+        }
+        job.append(text, 0.0, format);
+    }
+    ui.add(egui::Label::new(job).sense(egui::Sense::click()))
+        .on_hover_cursor(egui::CursorIcon::PointingHand)
+}
+
+/// The fields we changed next to the values someone else saved in the meantime.
+fn conflict_rows(ui: &mut egui::Ui, mine: &serde_json::Value, theirs: &serde_json::Value) {
+    let Some(changes) = mine.as_object() else { return };
+    egui::Grid::new("conflict_rows").striped(true).show(ui, |ui| {
+        ui.label("");
+        ui.strong("Theirs");
+        ui.strong("Yours");
+        ui.end_row();
+        for (field, value) in changes.iter().filter(|(field, _)| !matches!(field.as_str(), "id" | "version")) {
+            ui.label(field);
+            ui.label(conflict_value(theirs.get(field).unwrap_or(&serde_json::Value::Null)));
+            ui.label(conflict_value(value));
+            ui.end_row();
+        }
+    });
+}
+
+/// A field value for `conflict_rows`, long notes are cut short.
+fn conflict_value(value: &serde_json::Value) -> String {
+    let text = match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => "none".to_owned(),
+        other => other.to_string(),
+    };
+    match text.char_indices().nth(60) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+/// One entry of the history tab: when, who and what changed.
+fn activity_row(ui: &mut egui::Ui, a: &Activity) {
+    ui.horizontal_wrapped(|ui| {
+        ui.weak(a.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string());
+        ui.strong(&a.actor_email);
+        ui.label(activity_summary(a));
+    });
+    if matches!(a.action, ActivityAction::Update | ActivityAction::Move | ActivityAction::Reorder) {
+        if let Some(changes) = a.diff.as_object() {
+            ui.indent(("activity", a.id), |ui| {
+                for (field, change) in changes {
+                    let text = match (change.get("from"), change.get("to")) {
+                        (Some(from), Some(to)) => format!("{}: {} → {}", field, from, to),
+                        _ => format!("{}: {}", field, change),
+                    };
+                    ui.weak(text);
+                }
+            });
+        }
+    }
+    ui.separator();
+}
+
+/// "created task 'Buy milk'" and the like, titles and names come from the recorded diff.
+fn activity_summary(a: &Activity) -> String {
+    let entity = a.entity_type.as_str().replace('_', " ");
+    let name = ["title", "name", "email"]
+        .iter()
+        .find_map(|key| a.diff.get(*key).and_then(|v| v.as_str()));
+    let verb = match a.action {
+        ActivityAction::Create => "created",
+        ActivityAction::Update => "changed",
+        ActivityAction::Delete => "deleted",
+        ActivityAction::Restore => "restored",
+        ActivityAction::Move => "moved",
+        ActivityAction::Reorder => "reordered",
+        ActivityAction::Attach => {
+            return format!("attached label '{}' to {} #{}", name.unwrap_or_default(), entity, a.entity_id);
+        }
+        ActivityAction::Detach => {
+            return format!("detached label '{}' from {} #{}", name.unwrap_or_default(), entity, a.entity_id);
+        }
+    };
+    match name {
+        Some(name) => format!("{} {} '{}'", verb, entity, name),
+        None => format!("{} {} #{}", verb, entity, a.entity_id),
+    }
+}
+
+/// Chip in the color of the label, the text is black or white to stay readable.
+fn label_chip(l: &Label, selected: bool) -> impl egui::Widget {
+    let [r, g, b] = l.rgb().unwrap_or([128, 128, 128]);
+    let light = 299 * r as u32 + 587 * g as u32 + 114 * b as u32 > 150_000;
+    let text_color = if light { egui::Color32::BLACK } else { egui::Color32::WHITE };
+    let mut chip = egui::Button::new(egui::RichText::new(&l.name).color(text_color).small())
+        .fill(egui::Color32::from_rgb(r, g, b))
+        .small();
+    if selected {
+        chip = chip.stroke(egui::Stroke::new(2.0, text_color));
+    }
+    chip
+}
+
+/// Checklist of a task, nested below its row.
+fn checklist_rows(ui: &mut egui::Ui, action: &Action, task_id: i32, items: Option<&Vec<ChecklistItem>>) {
+    let items = match items {
+        Some(items) if !items.is_empty() => items,
+        _ => return,
+    };
+    ui.indent(("checklist", task_id), |ui| {
+        for item in items {
+            let mut done = item.done;
+            if ui.checkbox(&mut done, &item.title).changed() {
+                action.edit_checklist_item(PatchChecklistItem {
+                    id: item.id,
+                    done: Some(done),
+                    ..Default::default()
+                });
+            }
+        }
+    });
+}
+
+/// Tasks are due by the end of the picked day in the local time zone.
+fn end_of_day(date: NaiveDate) -> Option<DateTime<Utc>> {
+    date.and_hms_opt(23, 59, 59)?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// Move request for the task dropped from `from` onto `to` within one section.
+fn dropped_task(section: &[&Task], from: usize, to: usize) -> MoveTask {
+    let anchor = section[to].id;
+    if to < from {
+        MoveTask { id: section[from].id, before: Some(anchor), ..Default::default() }
+    } else {
+        MoveTask { id: section[from].id, after: Some(anchor), ..Default::default() }
     }
 }
 
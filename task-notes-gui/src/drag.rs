@@ -0,0 +1,54 @@
+/// Drag-to-reorder state for a vertical list of rows.
+///
+/// Call `row` for every row while laying out the list, then `finish` once,
+/// which returns `(from, to)` when a row was dropped on a new place.
+#[derive(Default)]
+pub struct DragList {
+    dragged: Option<usize>,
+    rects: Vec<egui::Rect>,
+}
+
+impl DragList {
+    /// Adds the drag handle of row `i`, the rest of the row is laid out by `add_contents`.
+    pub fn row(&mut self, ui: &mut egui::Ui, i: usize, add_contents: impl FnOnce(&mut egui::Ui)) {
+        if i == 0 {
+            self.rects.clear();
+        }
+        let response = ui.horizontal(|ui| {
+            let handle = ui.add(egui::Label::new("☰").sense(egui::Sense::drag()));
+            if handle.hovered() || self.dragged == Some(i) {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+            }
+            if handle.drag_started() {
+                self.dragged = Some(i);
+            }
+            add_contents(ui);
+        });
+        self.rects.push(response.response.rect);
+    }
+
+    /// Paints the drop marker and reports a finished move.
+    pub fn finish(&mut self, ui: &mut egui::Ui) -> Option<(usize, usize)> {
+        let from = self.dragged?;
+        let released = ui.input(|i| i.pointer.any_released());
+        if released {
+            self.dragged = None;
+        }
+        let pointer = ui.ctx().pointer_interact_pos()?;
+        let to = self.rects.iter().filter(|r| r.center().y < pointer.y).count();
+        if !released {
+            if let (Some(first), Some(last)) = (self.rects.first(), self.rects.last()) {
+                let y = self.rects.get(to).map_or(last.bottom(), |r| r.top());
+                ui.painter().hline(first.x_range(), y, ui.visuals().selection.stroke);
+            }
+            ui.ctx().request_repaint();
+            return None;
+        }
+        // dropping below the dragged row shifts the target up by one
+        let to = if to > from { to - 1 } else { to };
+        if to == from || to >= self.rects.len() {
+            return None;
+        }
+        Some((from, to))
+    }
+}
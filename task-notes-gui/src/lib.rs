@@ -1,6 +1,7 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
-#[cfg(target_arch = "wasm32")]
+mod drag;
+mod markdown;
 pub mod api;
 pub use app::TemplateApp;
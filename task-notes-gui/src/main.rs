@@ -5,9 +5,17 @@
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
     use std::sync::Arc;
+    use client::native::NativeTransport;
+    use client::Client;
 
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    // the backend to talk to and an api token to sign in with, see the README
+    let server_url = std::env::var("TASK_NOTES_URL").unwrap_or_else(|_| "http://localhost:8180/".to_owned());
+    let token = std::env::var("TASK_NOTES_TOKEN").ok();
+    let transport = NativeTransport::new(token).expect("failed to start the http runtime");
+    let server = Client::new(&server_url, Arc::new(transport));
+
     let native_options = eframe::NativeOptions {
         initial_window_size: Some([400.0, 300.0].into()),
         persist_window: true,
@@ -17,30 +25,30 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "eframe template",
         native_options,
-        Box::new(|cc| Box::new(task_notes_gui::TemplateApp::new(cc, None, None, String::default()))),
+        Box::new(|cc| Box::new(task_notes_gui::TemplateApp::new(cc, server))),
     )
 }
 
-#[cfg(target_arch = "wasm32")]
-use eframe::wasm_bindgen::JsCast;
-#[cfg(target_arch = "wasm32")]
-use eframe::web_sys::{Request, RequestInit, RequestMode, Response};
-use serde::{Deserialize, Serialize};
-use task_notes_gui::api::Update;
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::prelude::*;
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen_futures::JsFuture;
-
 // When compiling to web using trunk:
 #[cfg(target_arch = "wasm32")]
 fn main() {
+    use std::sync::Arc;
+    use client::web::WebTransport;
+    use client::Client;
+
     // Redirect `log` message to `console.log` and friends:
     eframe::WebLogger::init(log::LevelFilter::Debug).ok();
     let web_options = eframe::WebOptions::default();
 
+    // the backend serves the gui, unless built with `TASK_NOTES_URL` pointing elsewhere
+    let server_url = match option_env!("TASK_NOTES_URL") {
+        Some(url) => url.to_owned(),
+        None => eframe::web_sys::window()
+            .and_then(|w| w.location().origin().ok())
+            .unwrap_or_default(),
+    };
+
     use egui::{Style, Visuals};
-    use wasm_bindgen_futures::spawn_local;
 
     wasm_bindgen_futures::spawn_local(async move {
         eframe::WebRunner::new()
@@ -55,7 +63,7 @@ fn main() {
                     cc.egui_ctx.set_style(style);
                     Box::new(task_notes_gui::TemplateApp::new(
                         cc,
-                        "http://localhost:5000/".to_string()
+                        Client::new(&server_url, Arc::new(WebTransport))
                     ))
                 }),
             )
@@ -0,0 +1,167 @@
+use egui::RichText;
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag};
+
+/// Renders Markdown with headings, lists, emphasis, code and links.
+pub fn show(ui: &mut egui::Ui, markdown: &str) {
+    let mut renderer = Renderer::default();
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        renderer.event(ui, event);
+    }
+    renderer.flush(ui);
+}
+
+enum Inline {
+    Text(RichText),
+    Link(RichText, String),
+}
+
+#[derive(Default)]
+struct Renderer {
+    line: Vec<Inline>,
+    heading: Option<HeadingLevel>,
+    strong: bool,
+    emphasis: bool,
+    strikethrough: bool,
+    link: Option<String>,
+    // one entry per open list, the next number of ordered lists
+    lists: Vec<Option<u64>>,
+    bullet: Option<String>,
+    code_block: Option<String>,
+}
+
+impl Renderer {
+    fn event(&mut self, ui: &mut egui::Ui, event: Event<'_>) {
+        match event {
+            Event::Start(tag) => self.start(ui, tag),
+            Event::End(tag) => self.end(ui, tag),
+            Event::Text(text) => match self.code_block {
+                Some(ref mut code) => code.push_str(&text),
+                None => self.push(&text),
+            },
+            Event::Code(code) => self.line.push(Inline::Text(RichText::new(code.to_string()).code())),
+            Event::SoftBreak => self.push(" "),
+            Event::HardBreak => self.flush(ui),
+            Event::Rule => {
+                self.flush(ui);
+                ui.separator();
+            }
+            Event::TaskListMarker(done) => self.push(if done { "☑ " } else { "☐ " }),
+            Event::Html(html) => self.push(&html),
+            Event::FootnoteReference(name) => self.push(&format!("[{}]", name)),
+        }
+    }
+
+    fn start(&mut self, ui: &mut egui::Ui, tag: Tag<'_>) {
+        match tag {
+            Tag::Heading(level, _, _) => {
+                self.flush(ui);
+                self.heading = Some(level);
+            }
+            Tag::Paragraph | Tag::BlockQuote => self.flush(ui),
+            Tag::List(first) => {
+                self.flush(ui);
+                self.lists.push(first);
+            }
+            Tag::Item => {
+                self.flush(ui);
+                self.bullet = Some(match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}.", *n - 1)
+                    }
+                    _ => "•".to_owned(),
+                });
+            }
+            Tag::CodeBlock(_) => {
+                self.flush(ui);
+                self.code_block = Some(String::new());
+            }
+            Tag::Emphasis => self.emphasis = true,
+            Tag::Strong => self.strong = true,
+            Tag::Strikethrough => self.strikethrough = true,
+            Tag::Link(_, url, _) | Tag::Image(_, url, _) => self.link = Some(url.to_string()),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, ui: &mut egui::Ui, tag: Tag<'_>) {
+        match tag {
+            Tag::Heading(..) => {
+                self.flush(ui);
+                self.heading = None;
+            }
+            Tag::Paragraph => {
+                self.flush(ui);
+                if self.lists.is_empty() {
+                    ui.add_space(4.0);
+                }
+            }
+            Tag::BlockQuote | Tag::Item => self.flush(ui),
+            Tag::List(_) => {
+                self.flush(ui);
+                self.lists.pop();
+            }
+            Tag::CodeBlock(_) => {
+                if let Some(code) = self.code_block.take() {
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.label(RichText::new(code.trim_end()).code());
+                    });
+                }
+            }
+            Tag::Emphasis => self.emphasis = false,
+            Tag::Strong => self.strong = false,
+            Tag::Strikethrough => self.strikethrough = false,
+            Tag::Link(..) | Tag::Image(..) => self.link = None,
+            _ => {}
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        let mut rich = RichText::new(text);
+        match self.heading {
+            Some(HeadingLevel::H1) => rich = rich.size(24.0).strong(),
+            Some(HeadingLevel::H2) => rich = rich.size(20.0).strong(),
+            Some(_) => rich = rich.size(17.0).strong(),
+            None => {}
+        }
+        if self.strong {
+            rich = rich.strong();
+        }
+        if self.emphasis {
+            rich = rich.italics();
+        }
+        if self.strikethrough {
+            rich = rich.strikethrough();
+        }
+        self.line.push(match self.link {
+            Some(ref url) => Inline::Link(rich, url.clone()),
+            None => Inline::Text(rich),
+        });
+    }
+
+    /// Lays out the pending inline text as one wrapped block.
+    fn flush(&mut self, ui: &mut egui::Ui) {
+        if self.line.is_empty() {
+            return;
+        }
+        let indent = 16.0 * self.lists.len() as f32;
+        let bullet = self.bullet.take();
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            ui.add_space(indent);
+            if let Some(bullet) = bullet {
+                ui.label(format!("{} ", bullet));
+            }
+            for inline in self.line.drain(..) {
+                match inline {
+                    Inline::Text(text) => {
+                        ui.label(text);
+                    }
+                    Inline::Link(text, url) => {
+                        ui.hyperlink_to(text, url);
+                    }
+                }
+            }
+        });
+    }
+}